@@ -9,7 +9,16 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use url::Url;
 
-use crate::solution::Solution;
+use crate::{
+    encryption,
+    fetch::InputFetch,
+    git::{self, Revision, Synced},
+    inputs,
+    limits::Limits,
+    sandbox::Backend,
+    session::Session,
+    solution::Solution,
+};
 
 /// A representation of a particpating AOC project.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -17,7 +26,10 @@ pub struct AocProject {
     username: String,
     repo: Url,
     location: PathBuf,
-    input_cmd: String,
+    #[serde(default)]
+    input_cmd: Option<String>,
+    #[serde(default)]
+    input_fetch: Option<InputFetch>,
     entrypoint: String,
     language: String,
     #[serde(default)]
@@ -26,8 +38,47 @@ pub struct AocProject {
     is_solver: bool,
     #[serde(default)]
     skip_inputs: bool,
+    #[serde(default)]
+    supports_noop: bool,
+    #[serde(default)]
+    supports_session: bool,
+    #[serde(default)]
+    supports_parts: bool,
+    #[serde(default)]
+    build_cmd: Option<String>,
+    #[serde(default, rename = "ref")]
+    git_ref: Option<String>,
+    #[serde(default)]
+    sandbox: bool,
+    #[serde(default)]
+    sandbox_paths: Vec<PathBuf>,
+    #[serde(default)]
+    limits: Limits,
+    /// The variables holding every participant's session token.
+    #[serde(skip)]
+    session_vars: Vec<String>,
 }
 
+/// The outcome of building a project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildStatus {
+    /// The project has no build command.
+    NotConfigured,
+    /// The project was already built at its current commit.
+    Cached,
+    Built,
+}
+
+/// The record of the last successful build, stored in the project's git
+/// directory so that it does not affect the work tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct BuildRecord {
+    head: String,
+    build_cmd: String,
+}
+
+const BUILD_RECORD: &str = "aoc-tools-build.json";
+
 impl AocProject {
     pub fn username(&self) -> &str {
         &self.username
@@ -59,6 +110,34 @@ impl AocProject {
         self.skip_inputs
     }
 
+    /// Where to fetch the project's inputs from over HTTP, instead of running
+    /// its input command.
+    pub fn input_fetch(&self) -> Option<&InputFetch> {
+        self.input_fetch.as_ref()
+    }
+
+    /// Whether the project has either an input command or an input fetch.
+    pub fn has_input_source(&self) -> bool {
+        self.input_cmd.is_some() || self.input_fetch.is_some()
+    }
+
+    /// Whether the project exits immediately, without solving anything, when
+    /// the `AOC_NOOP` environment variable is set.
+    pub fn supports_noop(&self) -> bool {
+        self.supports_noop
+    }
+
+    /// Whether the project only runs the given part when the `AOC_PART`
+    /// environment variable is set to `1` or `2`.
+    pub fn supports_parts(&self) -> bool {
+        self.supports_parts
+    }
+
+    /// Whether the project can be run as a persistent [Session].
+    pub fn supports_session(&self) -> bool {
+        self.supports_session
+    }
+
     pub fn input_path(&self, year: usize, day: usize) -> Result<Option<PathBuf>> {
         let output = self
             .input_command(year, day)?
@@ -83,14 +162,219 @@ impl AocProject {
             )
         })?;
 
-        Ok(Some(self.join_with_location(raw_path)?))
+        Ok(Some(self.resolve_within_location(raw_path)?))
+    }
+
+    /// The revision of the project's code, or `None` if its location is not a
+    /// git repo of its own.
+    pub fn revision(&self) -> Result<Option<Revision>> {
+        git::revision(&self.location)
+            .with_context(|| format!("Failed to read revision for project: {}", self.username()))
+    }
+
+    /// The branch, tag, or commit to check out when syncing the project.
+    pub fn git_ref(&self) -> Option<&str> {
+        self.git_ref.as_deref()
+    }
+
+    /// Clone or update the project's repo at its location, checking out its
+    /// ref.
+    pub fn sync(&self) -> Result<Synced> {
+        git::sync(self.repo.as_str(), &self.location, self.git_ref())
+    }
+
+    /// Whether the project's solver and benchmarks are run in a sandbox.
+    pub fn sandbox(&self) -> bool {
+        self.sandbox
+    }
+
+    /// The sandbox backend to use for this project, or `None` if the project
+    /// is not sandboxed.
+    ///
+    /// A sandboxed project is never run outside of a sandbox, so this fails if
+    /// no sandbox is available on the host.
+    fn sandbox_backend(&self) -> Result<Option<&'static Backend>> {
+        if !self.sandbox {
+            return Ok(None);
+        }
+
+        Backend::detect()
+            .map(Some)
+            .map_err(|reason| anyhow!("Sandboxing is not available on this host: {}", reason))
+    }
+
+    /// The resource limits of the project's solver and benchmarks.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Fill in any limits the project does not set from the `defaults`.
+    pub fn inherit_limits(&mut self, defaults: Limits) {
+        self.limits = self.limits.or(defaults);
+    }
+
+    /// Keep the session tokens in the given variables, which may belong to any
+    /// participant, from the project's code.
+    pub fn inherit_session_vars(&mut self, vars: Vec<String>) {
+        self.session_vars = vars;
+    }
+
+    /// Remove the secrets the tools read from the environment from the
+    /// environment of the command, which runs the project's code.
+    ///
+    /// Every command that runs the project's code must be scrubbed, including
+    /// runners that run it through another program.
+    pub fn scrub_secrets(&self, cmd: &mut Command) {
+        cmd.env_remove(encryption::KEY_VAR);
+        cmd.env_remove(inputs::SALT_VAR);
+        for var in self.session_vars.iter() {
+            cmd.env_remove(var);
+        }
+    }
+
+    /// Run the command in the project's sandbox, if it is sandboxed, with
+    /// read-only access to its location, its `sandbox_paths`, and the
+    /// `readable` paths, and with the project's limits applied.
+    fn sandboxed(&self, mut cmd: Command, readable: &[&Path]) -> Result<Command> {
+        // the sandbox keeps the removals
+        self.scrub_secrets(&mut cmd);
+
+        let mut cmd = match self.sandbox_backend()? {
+            Some(backend) => {
+                let mut paths: Vec<&Path> =
+                    self.sandbox_paths.iter().map(PathBuf::as_path).collect();
+                paths.extend(readable);
+
+                backend.apply(cmd, &self.location, &paths)
+            }
+            None => cmd,
+        };
+
+        // the limits are inherited by the sandboxed process
+        self.limits.apply(&mut cmd);
+
+        Ok(cmd)
+    }
+
+    /// The shell command prefix that runs a command in the project's sandbox
+    /// in the given working directory, if it is sandboxed.
+    pub fn shell_sandbox_prefix(&self, cwd: &Path) -> Result<Option<String>> {
+        let Some(backend) = self.sandbox_backend()? else {
+            return Ok(None);
+        };
+
+        let mut paths: Vec<&Path> = self.sandbox_paths.iter().map(PathBuf::as_path).collect();
+        paths.push(cwd);
+
+        Ok(Some(backend.prefix(&self.location, &paths, cwd)))
+    }
+
+    pub fn build_cmd(&self) -> Option<&str> {
+        self.build_cmd.as_deref()
+    }
+
+    /// Run the project's build command, if it has one.
+    ///
+    /// The build is skipped if the project was last built with the same
+    /// command at its current git HEAD and the work tree is clean. Projects
+    /// that are not git repos of their own, including ones inside of another
+    /// repo, are always built.
+    pub fn build(&self) -> Result<BuildStatus> {
+        let Some(build_cmd) = self.build_cmd() else {
+            return Ok(BuildStatus::NotConfigured);
+        };
+
+        let record_path = if git::is_repo(&self.location) {
+            Some(git::git_dir(&self.location)?.join(BUILD_RECORD))
+        } else {
+            None
+        };
+
+        // only a clean work tree is guaranteed to match the commit
+        let record = match record_path {
+            Some(_) if !git::is_dirty(&self.location)? => Some(BuildRecord {
+                head: git::head(&self.location)?,
+                build_cmd: build_cmd.to_string(),
+            }),
+            _ => None,
+        };
+
+        if let (Some(path), Some(record)) = (&record_path, &record)
+            && let Ok(raw) = std::fs::read_to_string(path)
+            && serde_json::from_str::<BuildRecord>(&raw).is_ok_and(|prev| &prev == record)
+        {
+            return Ok(BuildStatus::Cached);
+        }
+
+        let output = self
+            .build_command()?
+            .output()
+            .context("Failed to execute build command")?;
+
+        if !output.status.success() {
+            bail!(
+                "Build failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        if let Some(path) = record_path {
+            match record {
+                Some(record) => std::fs::write(&path, serde_json::to_string(&record)?)
+                    .context("Failed to write build record")?,
+                // a build of a dirty work tree should not be reused
+                None if path.is_file() => {
+                    std::fs::remove_file(&path).context("Failed to remove build record")?
+                }
+                None => {}
+            }
+        }
+
+        Ok(BuildStatus::Built)
+    }
+
+    /// Construct a [Command] to build the project.
+    ///
+    /// This command is set up with the `current_dir` as the project's location.
+    pub fn build_command(&self) -> Result<Command> {
+        let raw = self
+            .build_cmd()
+            .ok_or_else(|| anyhow!("No build command for project: {}", self.username()))?;
+        let parts = shell_words::split(raw).with_context(|| {
+            format!(
+                "Failed to parse build command for project: {}",
+                &self.username()
+            )
+        })?;
+        let (prog, args) = parts.split_first().ok_or_else(|| {
+            anyhow!(
+                "Could not extract program for project: {}",
+                &self.username()
+            )
+        })?;
+
+        let mut cmd = Command::new(prog);
+
+        cmd.env("AOC_CI", "true");
+        cmd.current_dir(&self.location);
+        self.scrub_secrets(&mut cmd);
+
+        if !args.is_empty() {
+            cmd.args(args);
+        }
+
+        Ok(cmd)
     }
 
     /// Construct a [Command] to get the path to the input for a given day.
     ///
     /// This command is set up with the `current_dir` as the project's location.
     pub fn input_command(&self, year: usize, day: usize) -> Result<Command> {
-        let parts = shell_words::split(&self.input_cmd).with_context(|| {
+        let Some(ref input_cmd) = self.input_cmd else {
+            bail!("No input command for project: {}", self.username());
+        };
+        let parts = shell_words::split(input_cmd).with_context(|| {
             format!(
                 "Failed to parse input command for project: {}",
                 &self.username()
@@ -109,6 +393,7 @@ impl AocProject {
         cmd.env("AOC_DAY", day.to_string());
         cmd.env("AOC_CI", "true");
         cmd.current_dir(&self.location);
+        self.scrub_secrets(&mut cmd);
 
         if !args.is_empty() {
             cmd.args(args);
@@ -129,6 +414,44 @@ impl AocProject {
         Ok(self.location.join(path))
     }
 
+    /// Join the relative path with the location and resolve it to a regular
+    /// file that is actually inside the location.
+    ///
+    /// The path is canonicalized, so neither `..` nor symlinks can be used to
+    /// point at a file outside of the project.
+    pub fn resolve_within_location<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        let path = self.join_with_location(path)?;
+
+        let location = self.location.canonicalize().with_context(|| {
+            format!(
+                "Failed to resolve location: '{}'",
+                self.location.to_string_lossy()
+            )
+        })?;
+        let resolved = path.canonicalize().with_context(|| {
+            format!(
+                "'{}' does not exist or cannot be resolved",
+                path.to_string_lossy()
+            )
+        })?;
+
+        if !resolved.starts_with(&location) {
+            bail!(
+                "'{}' resolves to '{}', which is outside of '{}'",
+                path.to_string_lossy(),
+                resolved.to_string_lossy(),
+                location.to_string_lossy()
+            );
+        }
+
+        // canonical paths contain no symlinks, so this is the file itself
+        if !resolved.metadata()?.is_file() {
+            bail!("'{}' is not a regular file", resolved.to_string_lossy());
+        }
+
+        Ok(resolved)
+    }
+
     pub fn solve(
         &self,
         year: usize,
@@ -142,6 +465,10 @@ impl AocProject {
             .context("Failed to execute command")?;
 
         if !output.status.success() {
+            if let Some(exceeded) = self.limits.exceeded(&output.status, &output.stderr) {
+                return Err(exceeded.into());
+            }
+
             bail!("Failed to solve: {:?}", output);
         }
 
@@ -211,27 +538,116 @@ impl AocProject {
         cmd.env("AOC_JSON", "true");
         cmd.env("AOC_CI", "true");
 
-        Ok(cmd)
+        self.sandboxed(cmd, &[input])
+    }
+
+    /// Start a persistent [Session] with the project's solver.
+    ///
+    /// If the project is sandboxed, the inputs sent to the session must be
+    /// within the `readable` paths.
+    pub fn start_session(&self, readable: &[&Path]) -> Result<Session> {
+        if !self.supports_session() {
+            bail!("Project does not support sessions: {}", self.username());
+        }
+
+        Session::start(self.session_command(readable)?)
+            .with_context(|| format!("Failed to start session for project: {}", self.username()))
+    }
+
+    /// Get a command that runs the solver in session mode.
+    pub fn session_command(&self, readable: &[&Path]) -> Result<Command> {
+        let parts = shell_words::split(self.entrypoint()).with_context(|| {
+            format!(
+                "Failed to parse entrypoint command for project: {}",
+                self.username()
+            )
+        })?;
+
+        let (prog, args) = parts
+            .split_first()
+            .ok_or_else(|| anyhow!("Could not extract program"))?;
+
+        let mut cmd = Command::new(prog);
+
+        if !args.is_empty() {
+            cmd.args(args);
+        }
+
+        cmd.env("AOC_SESSION", "true");
+        cmd.env("AOC_JSON", "true");
+        cmd.env("AOC_CI", "true");
+
+        self.sandboxed(cmd, readable)
+    }
+
+    /// Get a command to benchmark the solution for a given day and absolute
+    /// path to an input.
+    ///
+    /// This uses the `bench_entrypoint`, if specified.
+    pub fn bench_command(&self, year: usize, day: usize, input: &Path) -> Result<Command> {
+        if input.is_relative() {
+            bail!("Inputs provided to the benchmark must be absolute");
+        }
+
+        let parts = shell_words::split(self.bench_entrypoint()).with_context(|| {
+            format!(
+                "Failed to parse bench entrypoint command for project: {}",
+                self.username()
+            )
+        })?;
+
+        let (prog, args) = parts
+            .split_first()
+            .ok_or_else(|| anyhow!("Could not extract program"))?;
+
+        let mut cmd = Command::new(prog);
+
+        if !args.is_empty() {
+            cmd.args(args);
+        }
+
+        cmd.env("AOC_YEAR", year.to_string());
+        cmd.env("AOC_DAY", day.to_string());
+        cmd.env("AOC_INPUT", input.to_string_lossy().to_string());
+        cmd.env("AOC_CI", "true");
+
+        self.sandboxed(cmd, &[input])
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use assert_fs::{TempDir, prelude::*};
+
     use super::*;
 
-    #[test]
-    fn join_with_location() {
-        let project = AocProject {
+    fn project(location: &Path) -> AocProject {
+        AocProject {
             username: "mattcl".into(),
             repo: Url::from_str("https://ancalagon.black/foo").unwrap(),
-            location: "/foo/bar".into(),
-            input_cmd: "echo 'not implemented'".into(),
+            location: location.into(),
+            input_cmd: Some("echo 'not implemented'".into()),
+            input_fetch: None,
             entrypoint: "echo 'not implemented'".into(),
             language: "cobol".into(),
             bench_entrypoint: None,
             is_solver: false,
             skip_inputs: false,
-        };
+            supports_noop: false,
+            supports_session: false,
+            supports_parts: false,
+            build_cmd: None,
+            git_ref: None,
+            sandbox: false,
+            sandbox_paths: Vec::default(),
+            limits: Limits::default(),
+            session_vars: Vec::default(),
+        }
+    }
+
+    #[test]
+    fn join_with_location() {
+        let project = project(Path::new("/foo/bar"));
 
         let expected = PathBuf::from_str("/foo/bar/baz.txt").unwrap();
         let joined = project.join_with_location("./baz.txt").unwrap();
@@ -241,4 +657,128 @@ mod tests {
         let joined = project.join_with_location("baz.txt").unwrap();
         assert_eq!(joined, expected);
     }
+
+    #[test]
+    fn resolve_within_location() {
+        let dir = TempDir::new().unwrap();
+        let location = dir.child("project");
+        location.child("inputs/day1.txt").write_str("1\n").unwrap();
+        dir.child("secret.txt").write_str("secret\n").unwrap();
+        location
+            .child("escape")
+            .symlink_to_file(dir.child("secret.txt").path())
+            .unwrap();
+        location
+            .child("inside")
+            .symlink_to_file(location.child("inputs/day1.txt").path())
+            .unwrap();
+
+        let project = project(location.path());
+
+        let expected = location.child("inputs/day1.txt").canonicalize().unwrap();
+        assert_eq!(
+            project.resolve_within_location("inputs/day1.txt").unwrap(),
+            expected
+        );
+        assert_eq!(project.resolve_within_location("inside").unwrap(), expected);
+
+        for path in [
+            "../secret.txt",
+            "escape",
+            "inputs",
+            "missing.txt",
+            "/etc/passwd",
+        ] {
+            assert!(project.resolve_within_location(path).is_err(), "{}", path);
+        }
+    }
+
+    fn git(location: &Path, args: &[&str]) {
+        let output = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(location)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+    }
+
+    #[test]
+    fn build() {
+        let dir = TempDir::new().unwrap();
+        let location = dir.child("alice");
+        location.child("file.txt").write_str("1\n").unwrap();
+        git(dir.path(), &["init", "-q"]);
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-qm", "parent"]);
+
+        let mut project = project(location.path());
+        project.build_cmd = Some("true".into());
+
+        // the parent's commit says nothing about the project's code
+        assert_eq!(project.build().unwrap(), BuildStatus::Built);
+        assert_eq!(project.build().unwrap(), BuildStatus::Built);
+
+        git(location.path(), &["init", "-q"]);
+        git(location.path(), &["add", "."]);
+        git(location.path(), &["commit", "-qm", "alice"]);
+        assert_eq!(project.build().unwrap(), BuildStatus::Built);
+        assert_eq!(project.build().unwrap(), BuildStatus::Cached);
+
+        location.child("file.txt").write_str("2\n").unwrap();
+        assert_eq!(project.build().unwrap(), BuildStatus::Built);
+    }
+
+    #[test]
+    fn scrub_secrets() {
+        let mut project = project(Path::new("/foo/bar"));
+        project.build_cmd = Some("make".into());
+        let input = Path::new("/foo/input");
+
+        for cmd in [
+            project.build_command().unwrap(),
+            project.input_command(2022, 1).unwrap(),
+            project.solver_command(2022, 1, input, None).unwrap(),
+            project.bench_command(2022, 1, input).unwrap(),
+        ] {
+            let removed: Vec<_> = cmd
+                .get_envs()
+                .filter(|(_, value)| value.is_none())
+                .map(|(key, _)| key.to_string_lossy().to_string())
+                .collect();
+            assert!(removed.iter().any(|k| k == encryption::KEY_VAR));
+            assert!(removed.iter().any(|k| k == inputs::SALT_VAR));
+        }
+    }
+
+    #[test]
+    fn solver_does_not_see_session_tokens() {
+        // set by cargo for every test
+        let var = "CARGO_PKG_NAME";
+        if std::env::var(var).is_err() {
+            return;
+        }
+
+        let dir = TempDir::new().unwrap();
+        let input = dir.child("input");
+        input.write_str("1\n").unwrap();
+
+        let mut project = project(dir.path());
+        project.entrypoint = format!("sh -c 'echo \"${{{}:-unset}}\"'", var);
+
+        let output = project
+            .solver_command(2022, 1, input.path(), None)
+            .unwrap()
+            .output()
+            .unwrap();
+        assert_ne!(String::from_utf8_lossy(&output.stdout).trim(), "unset");
+
+        project.inherit_session_vars(vec![var.to_string()]);
+        let output = project
+            .solver_command(2022, 1, input.path(), None)
+            .unwrap()
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "unset");
+    }
 }
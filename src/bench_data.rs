@@ -1,8 +1,14 @@
-use std::path::Path;
+use std::{
+    collections::BTreeMap,
+    ops::{Deref, DerefMut},
+    path::Path,
+};
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
+use crate::{bench_runner::CacheMode, git::Revision, inputs::line_count};
+
 #[derive(Debug, Default, Clone, Deserialize)]
 pub struct OriginalCSVRow {
     command: String,
@@ -37,6 +43,57 @@ pub struct BenchCSVRow {
     pub system: f64,
     pub min: f64,
     pub max: f64,
+    /// The mean startup time of the participant in seconds, if measured.
+    #[serde(default)]
+    pub startup: Option<f64>,
+    /// The mean with the startup time subtracted, if measured.
+    #[serde(default)]
+    pub adjusted: Option<f64>,
+    /// The mean peak resident set size in bytes, if measured.
+    ///
+    /// The native runner cannot measure below its own RSS, so small values are
+    /// an upper bound rather than exact.
+    #[serde(default)]
+    pub peak_rss_mean: Option<u64>,
+    /// The largest peak resident set size in bytes, if measured.
+    #[serde(default)]
+    pub peak_rss_max: Option<u64>,
+    /// The mean number of user-space instructions retired, if measured.
+    #[serde(default)]
+    pub instructions: Option<u64>,
+    /// The mean number of user-space CPU cycles, if measured.
+    #[serde(default)]
+    pub cycles: Option<u64>,
+    /// The mean number of cache misses, if measured.
+    #[serde(default)]
+    pub cache_misses: Option<u64>,
+    /// The mean number of branch misses, if measured.
+    #[serde(default)]
+    pub branch_misses: Option<u64>,
+    /// The part that was run, if only one of the parts was run.
+    #[serde(default)]
+    pub part: Option<u8>,
+    /// The size of the input in bytes, if known.
+    #[serde(default)]
+    pub input_bytes: Option<u64>,
+    /// The number of lines in the input, if known.
+    #[serde(default)]
+    pub input_lines: Option<u64>,
+    /// The input size divided by the mean, if the size is known.
+    #[serde(default)]
+    pub bytes_per_sec: Option<f64>,
+    /// The input lines divided by the mean, if the lines are known.
+    #[serde(default)]
+    pub lines_per_sec: Option<f64>,
+    /// The state of the page cache for the input at the start of each run.
+    #[serde(default)]
+    pub cache: CacheMode,
+    /// The commit of the participant's code that was benchmarked, if known.
+    #[serde(default)]
+    pub commit: Option<String>,
+    /// Whether the participant's code had uncommitted changes, if known.
+    #[serde(default)]
+    pub dirty: Option<bool>,
 }
 
 impl BenchCSVRow {
@@ -60,10 +117,251 @@ impl BenchCSVRow {
             system: original.system,
             min: original.min,
             max: original.max,
+            // hyperfine does not report memory usage
+            ..Default::default()
         }
     }
 }
 
+/// A single measured run of a benchmark.
+///
+/// Times are in seconds and the peak resident set size is in bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub wall: f64,
+    pub user: f64,
+    pub system: f64,
+    pub max_rss: u64,
+    pub counters: Counters,
+}
+
+/// Hardware performance counter values for a single run.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Counters {
+    pub instructions: Option<u64>,
+    pub cycles: Option<u64>,
+    pub cache_misses: Option<u64>,
+    pub branch_misses: Option<u64>,
+}
+
+impl BenchCSVRow {
+    /// Record the startup time of the participant, computing the adjusted
+    /// mean.
+    pub fn set_startup(&mut self, startup: f64) {
+        self.startup = Some(startup);
+        self.adjusted = Some((self.mean - startup).max(0.0));
+    }
+
+    /// Compute the summary statistics for a set of samples.
+    pub fn from_samples(
+        samples: &[Sample],
+        year: usize,
+        day: usize,
+        participant: String,
+        input: String,
+        language: String,
+    ) -> Self {
+        let mut wall: Vec<f64> = samples.iter().map(|s| s.wall).collect();
+        wall.sort_by(f64::total_cmp);
+
+        let n = wall.len().max(1) as f64;
+        let mean = wall.iter().sum::<f64>() / n;
+        let stddev = if wall.len() > 1 {
+            (wall.iter().map(|w| (w - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        let median = match wall.len() {
+            0 => 0.0,
+            x if x % 2 == 0 => (wall[x / 2 - 1] + wall[x / 2]) / 2.0,
+            x => wall[x / 2],
+        };
+
+        Self {
+            year,
+            day,
+            participant,
+            input,
+            language,
+            mean,
+            stddev,
+            median,
+            user: samples.iter().map(|s| s.user).sum::<f64>() / n,
+            system: samples.iter().map(|s| s.system).sum::<f64>() / n,
+            min: wall.first().copied().unwrap_or_default(),
+            max: wall.last().copied().unwrap_or_default(),
+            startup: None,
+            adjusted: None,
+            peak_rss_mean: Some(
+                (samples.iter().map(|s| s.max_rss as f64).sum::<f64>() / n).round() as u64,
+            ),
+            peak_rss_max: samples.iter().map(|s| s.max_rss).max(),
+            instructions: counter_mean(samples, |c| c.instructions),
+            cycles: counter_mean(samples, |c| c.cycles),
+            cache_misses: counter_mean(samples, |c| c.cache_misses),
+            branch_misses: counter_mean(samples, |c| c.branch_misses),
+            part: None,
+            input_bytes: None,
+            input_lines: None,
+            bytes_per_sec: None,
+            lines_per_sec: None,
+            cache: CacheMode::default(),
+            commit: None,
+            dirty: None,
+        }
+    }
+
+    /// Record the revision of the participant's code that was benchmarked.
+    pub fn set_revision(&mut self, revision: Option<&Revision>) {
+        self.commit = revision.map(|r| r.commit.clone());
+        self.dirty = revision.map(|r| r.dirty);
+    }
+
+    /// Record the size of the input, computing the throughput from the mean.
+    pub fn set_input_size(&mut self, bytes: u64, lines: u64) {
+        self.input_bytes = Some(bytes);
+        self.input_lines = Some(lines);
+
+        if self.mean > 0.0 {
+            self.bytes_per_sec = Some(bytes as f64 / self.mean);
+            self.lines_per_sec = Some(lines as f64 / self.mean);
+        }
+    }
+}
+
+/// The raw wall times in seconds of every measured run of a benchmark.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchSamples {
+    pub participant: String,
+    pub input: String,
+    /// The name of the runner that took the samples, which is empty for
+    /// samples recorded before it was.
+    #[serde(default)]
+    pub runner: String,
+    /// The part that was run, if only one of the parts was run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub part: Option<u8>,
+    /// The state of the page cache for the input at the start of each run.
+    #[serde(default)]
+    pub cache: CacheMode,
+    pub times: Vec<f64>,
+}
+
+/// The participants of a day's benchmarks, with the revision of their code
+/// that was benchmarked, if known.
+///
+/// The names are stored as a list in participants.json, as they always have
+/// been, and the known revisions are stored by name in revisions.json.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(from = "Vec<String>")]
+pub struct Participants(BTreeMap<String, Option<Revision>>);
+
+/// The file listing the names of the participants.
+pub const PARTICIPANTS: &str = "participants.json";
+
+/// The file holding the revision of each participant's code.
+pub const REVISIONS: &str = "revisions.json";
+
+impl From<Vec<String>> for Participants {
+    fn from(names: Vec<String>) -> Self {
+        Self(names.into_iter().map(|n| (n, None)).collect())
+    }
+}
+
+impl Deref for Participants {
+    type Target = BTreeMap<String, Option<Revision>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Participants {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Participants {
+    /// Load the participants in the given day directory, along with their
+    /// revisions if recorded.
+    pub fn load<P: AsRef<Path>>(day_directory: P) -> Result<Self> {
+        let day_directory = day_directory.as_ref();
+
+        let contents = std::fs::read_to_string(day_directory.join(PARTICIPANTS))
+            .context("Failed to read participants file")?;
+        let mut participants: Self =
+            serde_json::from_str(&contents).context("Failed to parse participants file")?;
+
+        let revisions_file = day_directory.join(REVISIONS);
+        if revisions_file.is_file() {
+            let contents =
+                std::fs::read_to_string(revisions_file).context("Failed to read revisions file")?;
+            let revisions: BTreeMap<String, Revision> =
+                serde_json::from_str(&contents).context("Failed to parse revisions file")?;
+
+            for (name, revision) in revisions {
+                if let Some(entry) = participants.0.get_mut(&name) {
+                    *entry = Some(revision);
+                }
+            }
+        }
+
+        Ok(participants)
+    }
+
+    /// Write the participants and their revisions to the given day directory.
+    pub fn save<P: AsRef<Path>>(&self, day_directory: P) -> Result<()> {
+        let day_directory = day_directory.as_ref();
+
+        let names: Vec<_> = self.0.keys().collect();
+        let contents = serde_json::to_string(&names).context("Failed to write participants")?;
+        std::fs::write(day_directory.join(PARTICIPANTS), contents)
+            .context("Failed to write participants file")?;
+
+        let revisions: BTreeMap<_, _> = self
+            .0
+            .iter()
+            .filter_map(|(n, r)| r.as_ref().map(|r| (n, r)))
+            .collect();
+        let contents = serde_json::to_string(&revisions).context("Failed to write revisions")?;
+        std::fs::write(day_directory.join(REVISIONS), contents)
+            .context("Failed to write revisions file")
+    }
+}
+
+/// The size of an input file in bytes and lines.
+///
+/// A final line without a trailing newline is still counted.
+pub fn input_size<P: AsRef<Path>>(path: P) -> Result<(u64, u64)> {
+    let contents = std::fs::read(path).context("Failed to read input")?;
+    Ok((contents.len() as u64, line_count(&contents)))
+}
+
+impl BenchSamples {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Vec<Self>> {
+        let contents = std::fs::read_to_string(path).context("Failed to read samples file")?;
+        serde_json::from_str(&contents).context("Failed to parse samples file")
+    }
+}
+
+/// The mean of a counter over the samples, if every sample has a value for it.
+fn counter_mean<F>(samples: &[Sample], f: F) -> Option<u64>
+where
+    F: Fn(&Counters) -> Option<u64>,
+{
+    if samples.is_empty() {
+        return None;
+    }
+
+    let mut total = 0.0;
+    for sample in samples.iter() {
+        total += f(&sample.counters)? as f64;
+    }
+
+    Some((total / samples.len() as f64).round() as u64)
+}
+
 pub fn load_benches<P: AsRef<Path>>(path: P, out: &mut Vec<BenchCSVRow>) -> Result<()> {
     let mut reader = csv::Reader::from_path(path).context("Failed to parse csv")?;
 
@@ -73,3 +371,362 @@ pub fn load_benches<P: AsRef<Path>>(path: P, out: &mut Vec<BenchCSVRow>) -> Resu
 
     Ok(())
 }
+
+/// Load only the rows of the CSV that ran both parts, returning the number of
+/// rows for individual parts that were skipped.
+///
+/// Consumers that don't know about parts would otherwise count a participant
+/// several times for the same input.
+pub fn load_full_benches<P: AsRef<Path>>(path: P, out: &mut Vec<BenchCSVRow>) -> Result<usize> {
+    let mut rows = Vec::default();
+    load_benches(path, &mut rows)?;
+
+    let (full, parts): (Vec<_>, Vec<_>) = rows.into_iter().partition(|r| r.part.is_none());
+    out.extend(full);
+
+    Ok(parts.len())
+}
+
+/// A column that is only shown in the markdown table if at least one row has
+/// a value for it.
+type OptionalColumn = (&'static str, fn(&BenchCSVRow) -> Option<String>);
+
+/// Optional columns displayed immediately after the mean.
+const MEAN_COLUMNS: &[OptionalColumn] = &[
+    ("Startup [ms]", |r| {
+        r.startup.map(|s| format!("{:.1}", s * 1000.0))
+    }),
+    ("Adjusted [ms]", |r| {
+        r.adjusted.map(|a| format!("{:.1}", a * 1000.0))
+    }),
+];
+
+/// Optional columns displayed after the min and max.
+const OPTIONAL_COLUMNS: &[OptionalColumn] = &[
+    ("Peak RSS [MiB]", |r| {
+        r.peak_rss_max
+            .map(|bytes| format!("{:.1}", bytes as f64 / (1024.0 * 1024.0)))
+    }),
+    ("Instructions", |r| r.instructions.map(|i| i.to_string())),
+    ("Throughput [MiB/s]", |r| {
+        r.bytes_per_sec
+            .map(|b| format!("{:.1}", b / (1024.0 * 1024.0)))
+    }),
+    ("Throughput [lines/s]", |r| {
+        r.lines_per_sec.map(|l| format!("{:.0}", l))
+    }),
+];
+
+/// Render the given rows as a markdown table, sorted by mean runtime.
+///
+/// This mirrors the format of hyperfine's markdown export, with the command
+/// split into participant and input columns. Times are displayed in
+/// milliseconds. Additional metrics are only included if they were measured.
+///
+/// Rows for individual parts are excluded, see [parts_markdown_table].
+pub fn markdown_table(rows: &[BenchCSVRow]) -> String {
+    let mut sorted: Vec<_> = rows.iter().filter(|r| r.part.is_none()).collect();
+    sorted.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+
+    let present = |columns: &'static [OptionalColumn]| -> Vec<&OptionalColumn> {
+        columns
+            .iter()
+            .filter(|(_, f)| sorted.iter().any(|r| f(r).is_some()))
+            .collect()
+    };
+    let after_mean = present(MEAN_COLUMNS);
+    let optional = present(OPTIONAL_COLUMNS);
+    // the cache state is only interesting if anything was run cold
+    let show_cache = sorted.iter().any(|r| r.cache == CacheMode::Cold);
+
+    let mut header = "| Participant | Input |".to_string();
+    let mut alignment = "|:---|:---|".to_string();
+    if show_cache {
+        header.push_str(" Cache |");
+        alignment.push_str(":---|");
+    }
+    header.push_str(" Mean [ms] |");
+    alignment.push_str("---:|");
+    for (name, _) in after_mean.iter() {
+        header.push_str(&format!(" {} |", name));
+        alignment.push_str("---:|");
+    }
+    header.push_str(" Min [ms] | Max [ms] |");
+    alignment.push_str("---:|---:|");
+    for (name, _) in optional.iter() {
+        header.push_str(&format!(" {} |", name));
+        alignment.push_str("---:|");
+    }
+    header.push_str(" Relative |");
+    alignment.push_str("---:|");
+
+    let mut lines = vec![header, alignment];
+
+    let Some(fastest) = sorted.first().copied() else {
+        return lines.join("\n");
+    };
+
+    for row in sorted.iter() {
+        let relative = row.mean / fastest.mean;
+        let relative = if std::ptr::eq(*row, fastest) {
+            format!("{:.2}", relative)
+        } else {
+            // propagate the uncertainty of both means
+            let stddev = relative
+                * ((row.stddev / row.mean).powi(2) + (fastest.stddev / fastest.mean).powi(2))
+                    .sqrt();
+            format!("{:.2} ± {:.2}", relative, stddev)
+        };
+
+        let mut line = format!("| {} | {} |", row.participant, row.input);
+        if show_cache {
+            line.push_str(&format!(" {} |", row.cache.as_str()));
+        }
+        line.push_str(&format!(
+            " {:.1} ± {:.1} |",
+            row.mean * 1000.0,
+            row.stddev * 1000.0
+        ));
+        for (_, f) in after_mean.iter() {
+            line.push_str(&format!(" {} |", f(row).unwrap_or_else(|| "-".to_string())));
+        }
+        line.push_str(&format!(
+            " {:.1} | {:.1} |",
+            row.min * 1000.0,
+            row.max * 1000.0
+        ));
+        for (_, f) in optional.iter() {
+            line.push_str(&format!(" {} |", f(row).unwrap_or_else(|| "-".to_string())));
+        }
+        line.push_str(&format!(" {} |", relative));
+
+        lines.push(line);
+    }
+
+    lines.join("\n")
+}
+
+/// Render the time taken by each part as a markdown table, sorted by the
+/// mean runtime of running both parts.
+///
+/// Each part is run on its own, so the time for a part includes the startup
+/// and parsing that it shares with the other part. Returns `None` if no parts
+/// were timed.
+pub fn parts_markdown_table(rows: &[BenchCSVRow]) -> Option<String> {
+    let mut by_bench: BTreeMap<(&str, &str), [Option<&BenchCSVRow>; 3]> = BTreeMap::default();
+    for row in rows.iter() {
+        let idx = match row.part {
+            None => 0,
+            Some(1) => 1,
+            Some(2) => 2,
+            Some(_) => continue,
+        };
+        by_bench
+            .entry((row.participant.as_str(), row.input.as_str()))
+            .or_default()[idx] = Some(row);
+    }
+    by_bench.retain(|_, v| v[1].is_some() || v[2].is_some());
+
+    if by_bench.is_empty() {
+        return None;
+    }
+
+    let mut sorted: Vec<_> = by_bench.into_iter().collect();
+    sorted.sort_by(|a, b| {
+        let mean = |v: &[Option<&BenchCSVRow>; 3]| v[0].map(|r| r.mean).unwrap_or(f64::MAX);
+        mean(&a.1).total_cmp(&mean(&b.1))
+    });
+
+    let fmt = |row: Option<&BenchCSVRow>| {
+        row.map(|r| format!("{:.1} ± {:.1}", r.mean * 1000.0, r.stddev * 1000.0))
+            .unwrap_or_else(|| "-".to_string())
+    };
+
+    let mut lines = vec![
+        "| Participant | Input | Part 1 [ms] | Part 2 [ms] | Both [ms] |".to_string(),
+        "|:---|:---|---:|---:|---:|".to_string(),
+    ];
+
+    for ((participant, input), v) in sorted.iter() {
+        lines.push(format!(
+            "| {} | {} | {} | {} | {} |",
+            participant,
+            input,
+            fmt(v[1]),
+            fmt(v[2]),
+            fmt(v[0]),
+        ));
+    }
+
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn from_samples() {
+        let samples: Vec<_> = [0.4, 0.1, 0.3, 0.2]
+            .into_iter()
+            .map(|wall| Sample {
+                wall,
+                user: wall / 2.0,
+                system: 0.0,
+                max_rss: (wall * 10.0) as u64 * 1024,
+                counters: Counters {
+                    instructions: Some((wall * 1000.0) as u64),
+                    ..Default::default()
+                },
+            })
+            .collect();
+
+        let row = BenchCSVRow::from_samples(
+            &samples,
+            2023,
+            1,
+            "foo".into(),
+            "input-foo".into(),
+            "rust".into(),
+        );
+
+        assert!((row.mean - 0.25).abs() < 1e-9);
+        assert!((row.median - 0.25).abs() < 1e-9);
+        assert!((row.user - 0.125).abs() < 1e-9);
+        assert!((row.stddev - 0.129_099_444_9).abs() < 1e-9);
+        assert_eq!(row.min, 0.1);
+        assert_eq!(row.max, 0.4);
+        assert_eq!(row.peak_rss_mean, Some(2560));
+        assert_eq!(row.peak_rss_max, Some(4096));
+        assert_eq!(row.instructions, Some(250));
+        assert_eq!(row.cycles, None);
+    }
+
+    #[test]
+    fn load_without_memory_columns() {
+        let raw = "\
+year,day,participant,input,language,mean,stddev,median,user,system,min,max
+2023,1,foo,input-foo,rust,0.1,0.01,0.1,0.05,0.01,0.09,0.11
+";
+        let mut reader = csv::Reader::from_reader(raw.as_bytes());
+        let rows: Vec<BenchCSVRow> = reader.deserialize().collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].peak_rss_max, None);
+    }
+
+    #[test]
+    fn load_full_benches() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("benches_raw.csv");
+        std::fs::write(
+            &path,
+            "\
+year,day,participant,input,language,mean,stddev,median,user,system,min,max,part
+2023,1,foo,input-foo,rust,0.1,0.01,0.1,0.05,0.01,0.09,0.11,
+2023,1,foo,input-foo,rust,0.06,0.01,0.06,0.03,0.01,0.05,0.07,1
+2023,1,foo,input-foo,rust,0.05,0.01,0.05,0.02,0.01,0.04,0.06,2
+2023,1,bar,input-foo,go,0.2,0.01,0.2,0.1,0.01,0.19,0.21,
+",
+        )
+        .unwrap();
+
+        let mut rows = Vec::default();
+        let skipped = super::load_full_benches(&path, &mut rows).unwrap();
+
+        assert_eq!(skipped, 2);
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|r| r.part.is_none()));
+        assert_eq!(rows[0].participant, "foo");
+        assert_eq!(rows[0].mean, 0.1);
+    }
+
+    #[test]
+    fn markdown_table() {
+        let rows = vec![
+            BenchCSVRow {
+                participant: "slow".into(),
+                input: "input-a".into(),
+                mean: 0.004,
+                stddev: 0.0004,
+                min: 0.003,
+                max: 0.005,
+                ..Default::default()
+            },
+            BenchCSVRow {
+                participant: "fast".into(),
+                input: "input-a".into(),
+                mean: 0.002,
+                stddev: 0.0002,
+                min: 0.001,
+                max: 0.003,
+                ..Default::default()
+            },
+        ];
+
+        let expected = "\
+| Participant | Input | Mean [ms] | Min [ms] | Max [ms] | Relative |
+|:---|:---|---:|---:|---:|---:|
+| fast | input-a | 2.0 ± 0.2 | 1.0 | 3.0 | 1.00 |
+| slow | input-a | 4.0 ± 0.4 | 3.0 | 5.0 | 2.00 ± 0.28 |";
+
+        assert_eq!(super::markdown_table(&rows), expected);
+    }
+
+    #[test]
+    fn parts_markdown_table() {
+        let row = |participant: &str, part: Option<u8>, mean: f64| BenchCSVRow {
+            participant: participant.into(),
+            input: "input-a".into(),
+            mean,
+            stddev: mean / 10.0,
+            part,
+            ..Default::default()
+        };
+
+        let rows = vec![
+            row("slow", None, 0.004),
+            row("slow", Some(1), 0.001),
+            row("slow", Some(2), 0.003),
+            row("fast", None, 0.002),
+            row("whole", None, 0.001),
+        ];
+
+        let expected = "\
+| Participant | Input | Part 1 [ms] | Part 2 [ms] | Both [ms] |
+|:---|:---|---:|---:|---:|
+| slow | input-a | 1.0 ± 0.1 | 3.0 ± 0.3 | 4.0 ± 0.4 |";
+
+        assert_eq!(super::parts_markdown_table(&rows).unwrap(), expected);
+        assert!(super::parts_markdown_table(&rows[3..]).is_none());
+
+        // individual parts are excluded from the main table
+        assert_eq!(super::markdown_table(&rows).lines().count(), 5);
+    }
+
+    #[test]
+    fn save_participants() {
+        let dir = TempDir::new().unwrap();
+        let mut participants = Participants::default();
+        participants.insert(
+            "alice".into(),
+            Some(Revision {
+                commit: "abc".into(),
+                dirty: false,
+            }),
+        );
+        participants.insert("bob".into(), None);
+
+        participants.save(dir.path()).unwrap();
+
+        // the participants file stays a list of names
+        let names: Vec<String> =
+            serde_json::from_str(&std::fs::read_to_string(dir.path().join(PARTICIPANTS)).unwrap())
+                .unwrap();
+        assert_eq!(names, ["alice", "bob"]);
+
+        assert_eq!(Participants::load(dir.path()).unwrap(), participants);
+    }
+}
@@ -0,0 +1,404 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    os::unix::process::ExitStatusExt,
+    path::Path,
+    process::{Command, ExitStatus},
+};
+
+use anyhow::{Context, Result, bail};
+use itertools::Itertools;
+use serde::Deserialize;
+use which::which;
+
+use crate::{
+    aoc_project::AocProject,
+    attention,
+    bench_data::{BenchCSVRow, BenchSamples, OriginalCSVRow},
+    failure, highlight,
+    limits::Limits,
+};
+
+use super::{CacheMode, RunResults, RunSettings, Runner};
+
+/// The subset of hyperfine's JSON export that we care about.
+#[derive(Debug, Deserialize)]
+struct JsonExport {
+    results: Vec<JsonResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonResult {
+    command: String,
+    times: Vec<f64>,
+    /// The exit code of each run, with signals reported as 128 plus the
+    /// signal number.
+    #[serde(default)]
+    exit_codes: Vec<Option<i32>>,
+    #[serde(default)]
+    parameters: BTreeMap<String, String>,
+}
+
+/// A participant and input for which at least one run failed.
+#[derive(Debug)]
+struct Failure {
+    participant: String,
+    input: String,
+    /// The exit code of the first failed run, if hyperfine knew it.
+    code: Option<i32>,
+}
+
+impl Failure {
+    /// Why the run failed, which is one of the `limits` if it was stopped by
+    /// one.
+    fn reason(&self, limits: &Limits) -> String {
+        let Some(code) = self.code else {
+            return "Benchmark command did not exit successfully".to_string();
+        };
+
+        // stderr is discarded by hyperfine, so only limits enforced by signals
+        // are seen
+        let status = ExitStatus::from_raw((code & 0xff) << 8);
+        match limits.exceeded(&status, &[]) {
+            Some(exceeded) => exceeded.to_string(),
+            None => format!("Benchmark command did not exit successfully ({})", status),
+        }
+    }
+}
+
+pub fn run(
+    year: usize,
+    day: usize,
+    day_directory: &Path,
+    inputs: &[String],
+    candidates: &[(&String, &AocProject)],
+    settings: &RunSettings,
+) -> Result<RunResults> {
+    if which("hyperfine").is_err() {
+        bail!("hyperfine must be installed");
+    }
+
+    // hyperfine takes commands as strings, so limits are applied with prlimit
+    if candidates.iter().any(|(_, p)| !p.limits().is_empty()) && which("prlimit").is_err() {
+        bail!("prlimit must be installed to apply resource limits with hyperfine");
+    }
+
+    // there is no flag for memory, so the empty columns are always explained
+    println!(
+        "  {}",
+        highlight!(
+            "Peak memory usage is not measured by hyperfine. Use the native runner to measure it."
+        )
+    );
+
+    if settings.perf_counters {
+        println!(
+            "  {}",
+            highlight!("Performance counters are not supported by hyperfine. Ignoring.")
+        );
+    }
+
+    if settings.interleave.is_some() {
+        println!(
+            "  {}",
+            highlight!("Interleaving is not supported by hyperfine. Ignoring.")
+        );
+    }
+
+    if candidates.iter().any(|(_, p)| p.supports_parts()) {
+        println!(
+            "  {}",
+            highlight!("Timing individual parts is not supported by hyperfine. Ignoring.")
+        );
+    }
+
+    let raw_csv = day_directory.join("hyperfine_raw.csv");
+    let raw_json = day_directory.join("hyperfine_raw.json");
+    let raw_markdown = day_directory.join("hyperfine_raw.md");
+
+    let mut cmd = Command::new("hyperfine");
+    cmd.current_dir(day_directory);
+    cmd.env("AOC_YEAR", year.to_string());
+    cmd.env("AOC_DAY", day.to_string());
+    cmd.env("AOC_CI", "true");
+    cmd.args([
+        "-w",
+        &settings.warmup.to_string(),
+        "-m",
+        &settings.min_runs.to_string(),
+        "-M",
+        &settings.max_runs.to_string(),
+        // iterate for each input
+        "-L",
+        "input",
+        &inputs.iter().join(","),
+        // sort by the execution time instead of order of specification
+        "--sort",
+        "mean-time",
+    ]);
+    if settings.cache == CacheMode::Cold {
+        // dd drops the whole file from the page cache when reading nothing
+        // with nocache
+        cmd.args([
+            "--prepare",
+            "dd if={input} iflag=nocache count=0 status=none",
+        ]);
+    }
+    // one participant failing, such as by exceeding a limit, must not stop
+    // everyone else from being benchmarked
+    cmd.arg("--ignore-failure");
+    // the json is only needed for the individual run times and exit codes
+    cmd.arg("--export-csv").arg(&raw_csv);
+    cmd.arg("--export-json").arg(&raw_json);
+    cmd.arg("--export-markdown").arg(&raw_markdown);
+
+    // the command run for each candidate, which is how we map hyperfine's
+    // results back to participants
+    let mut commands = Vec::with_capacity(candidates.len());
+    for (name, project) in candidates.iter() {
+        let mut command = match project.shell_sandbox_prefix(day_directory)? {
+            Some(prefix) => format!("{} {}", prefix, project.bench_entrypoint()),
+            None => project.bench_entrypoint().to_string(),
+        };
+        if let Some(prefix) = project.limits().prefix()? {
+            command = format!("{} {}", prefix, command);
+        }
+        cmd.arg(format!("AOC_INPUT={{input}} {}", command));
+        // hyperfine passes its environment on to every command
+        project.scrub_secrets(&mut cmd);
+        commands.push((command, *name, *project));
+    }
+
+    let status = cmd.status().context("Failed to execute hyperfine")?;
+    if !status.success() {
+        bail!("hyperfine did not exit successfully");
+    }
+
+    // Hyperfine's combinations of command names and inputs don't allow for
+    // what we want to do, so we map the commands back to participants.
+    let mut rows = transform_bench_csv(&raw_csv, year, day, &commands)
+        .context("Could not transform bench csv")?;
+
+    let (mut samples, failures) =
+        transform_bench_json(&raw_json, &commands).context("Could not transform bench json")?;
+
+    // hyperfine's table would still list the failed participants
+    let markdown = if failures.is_empty() {
+        Some(
+            transform_bench_markdown(&raw_markdown, inputs, &commands)
+                .context("Could not transform bench markdown")?,
+        )
+    } else {
+        None
+    };
+
+    let mut failed = HashSet::new();
+    for f in failures.iter() {
+        let limits = candidates
+            .iter()
+            .find(|(name, _)| **name == f.participant)
+            .map(|(_, project)| *project.limits())
+            .unwrap_or_default();
+        println!(
+            "  {}",
+            failure!(format!(
+                "{} | {}: {}",
+                f.participant,
+                f.input,
+                f.reason(&limits)
+            ))
+        );
+        failed.insert((f.participant.as_str(), f.input.as_str()));
+    }
+    rows.retain(|r| !failed.contains(&(r.participant.as_str(), r.input.as_str())));
+    samples.retain(|s| !failed.contains(&(s.participant.as_str(), s.input.as_str())));
+
+    std::fs::remove_file(&raw_csv).context("Failed to remove hyperfine output")?;
+    std::fs::remove_file(&raw_json).context("Failed to remove hyperfine output")?;
+    std::fs::remove_file(&raw_markdown).context("Failed to remove hyperfine output")?;
+
+    Ok(RunResults {
+        rows,
+        samples,
+        markdown,
+    })
+}
+
+/// Rewrite hyperfine's markdown table so that each command is replaced by
+/// participant and input columns.
+///
+/// This is memory inefficient with all the string replacements, but it
+/// probably won't be too bad.
+fn transform_bench_markdown<P: AsRef<Path>>(
+    path: P,
+    inputs: &[String],
+    commands: &[(String, &String, &AocProject)],
+) -> Result<String> {
+    let mut contents = std::fs::read_to_string(path)?;
+
+    // we're going to change the header of the markdown table
+    contents = contents.replacen("Command", "Participant | Input", 1);
+    // and the alignment spec
+    contents = contents.replacen(":---", ":---|:---", 1);
+
+    // now we're going to replace the command name and add the inputs in a
+    // separate column.
+    for (command, name, _) in commands.iter() {
+        for input in inputs.iter() {
+            let needle = format!("`AOC_INPUT={} {}`", input, command);
+            let replacement = format!("{} | {}", name, input);
+            contents = contents.replacen(&needle, &replacement, 1);
+        }
+    }
+
+    Ok(contents)
+}
+
+/// The samples of every successful participant and input, and the failures.
+fn transform_bench_json<P: AsRef<Path>>(
+    path: P,
+    commands: &[(String, &String, &AocProject)],
+) -> Result<(Vec<BenchSamples>, Vec<Failure>)> {
+    let raw = std::fs::read_to_string(path)?;
+    let export: JsonExport = serde_json::from_str(&raw)?;
+
+    let lookup_map: HashMap<&str, &str> = commands
+        .iter()
+        .map(|(command, name, _)| (command.as_str(), name.as_str()))
+        .collect();
+
+    let mut out = Vec::with_capacity(export.results.len());
+    let mut failures = Vec::default();
+
+    for result in export.results {
+        let Some(input) = result.parameters.get("input") else {
+            continue;
+        };
+
+        let entrypoint = result.command.replace(&format!("AOC_INPUT={} ", input), "");
+
+        if let Some(name) = lookup_map.get(entrypoint.as_str()) {
+            if let Some(code) = result.exit_codes.iter().find(|c| **c != Some(0)) {
+                failures.push(Failure {
+                    participant: name.to_string(),
+                    input: input.clone(),
+                    code: *code,
+                });
+                continue;
+            }
+
+            out.push(BenchSamples {
+                participant: name.to_string(),
+                input: input.clone(),
+                runner: Runner::Hyperfine.name().to_string(),
+                part: None,
+                cache: CacheMode::default(),
+                times: result.times,
+            });
+        }
+    }
+
+    Ok((out, failures))
+}
+
+fn transform_bench_csv<P: AsRef<Path>>(
+    path: P,
+    year: usize,
+    day: usize,
+    commands: &[(String, &String, &AocProject)],
+) -> Result<Vec<BenchCSVRow>> {
+    let path = path.as_ref();
+    let raw_bench_data: Vec<OriginalCSVRow> = {
+        let mut out = Vec::default();
+        let mut reader = csv::Reader::from_path(path)?;
+        for result in reader.deserialize() {
+            out.push(result?);
+        }
+        out
+    };
+
+    let lookup_map: HashMap<&str, (&str, &str)> = commands
+        .iter()
+        .map(|(command, name, project)| (command.as_str(), (name.as_str(), project.language())))
+        .collect();
+
+    let mut transformed = Vec::with_capacity(raw_bench_data.len());
+
+    for record in raw_bench_data {
+        let entrypoint = record.get_raw_command();
+        if let Some((name, language)) = lookup_map.get(&entrypoint.as_str()) {
+            transformed.push(BenchCSVRow::from_original(
+                record,
+                year,
+                day,
+                name.to_string(),
+                language.to_string(),
+            ));
+        } else {
+            println!(
+                "  {}",
+                attention!(format!("Could not transform record for `{}`", &entrypoint))
+            );
+        }
+    }
+
+    Ok(transformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::{TempDir, prelude::*};
+
+    use super::*;
+
+    #[test]
+    fn failures() {
+        let project: AocProject = serde_json::from_value(serde_json::json!({
+            "username": "alice",
+            "repo": "https://example.com/alice",
+            "location": "/foo",
+            "entrypoint": "run",
+            "language": "rust",
+        }))
+        .unwrap();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let commands = vec![
+            ("run-alice".to_string(), &alice, &project),
+            ("run-bob".to_string(), &bob, &project),
+        ];
+
+        let dir = TempDir::new().unwrap();
+        let json = dir.child("hyperfine_raw.json");
+        json.write_str(
+            r#"{"results": [
+                {"command": "AOC_INPUT=input-a run-alice", "times": [0.1, 0.2],
+                 "exit_codes": [0, 0], "parameters": {"input": "input-a"}},
+                {"command": "AOC_INPUT=input-a run-bob", "times": [0.1, 0.2],
+                 "exit_codes": [0, 152], "parameters": {"input": "input-a"}}
+            ]}"#,
+        )
+        .unwrap();
+
+        let (samples, failures) = transform_bench_json(json.path(), &commands).unwrap();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].participant, "alice");
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].participant, "bob");
+        assert_eq!(failures[0].code, Some(152));
+
+        let limits = Limits {
+            cpu_seconds: Some(1),
+            ..Limits::default()
+        };
+        assert!(
+            failures[0]
+                .reason(&limits)
+                .starts_with("Exceeded the CPU time limit")
+        );
+        assert!(
+            failures[0]
+                .reason(&Limits::default())
+                .starts_with("Benchmark command did not exit successfully")
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::{collections::HashMap, path::Path, time::Duration};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aoc_project::AocProject,
+    bench_data::{BenchCSVRow, BenchSamples},
+};
+
+mod hyperfine;
+mod native;
+mod perf;
+mod warm;
+
+/// The available ways of running benchmarks.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Runner {
+    /// Use hyperfine, which must be installed, along with prlimit if any
+    /// resource limits are set.
+    #[default]
+    Hyperfine,
+    /// Use the built-in runner.
+    Native,
+    /// Use the built-in runner with persistent sessions, excluding startup.
+    ///
+    /// Participants that do not support sessions are skipped.
+    Warm,
+}
+
+/// The state of the page cache for the inputs at the start of each run.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheMode {
+    /// Leave the inputs in whatever state the previous run left them, which
+    /// is almost always cached.
+    #[default]
+    Warm,
+    /// Evict the inputs from the page cache before each run.
+    Cold,
+}
+
+impl CacheMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Warm => "warm",
+            Self::Cold => "cold",
+        }
+    }
+}
+
+/// Settings controlling how many times each benchmark is run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunSettings {
+    /// The number of runs to perform before measuring.
+    pub warmup: usize,
+    /// The minimum number of measured runs.
+    pub min_runs: usize,
+    /// The maximum number of measured runs.
+    pub max_runs: usize,
+    /// The target total time in seconds to spend measuring a benchmark.
+    ///
+    /// This is only used by the native runner, which uses it to determine the
+    /// number of runs between `min_runs` and `max_runs`.
+    pub min_time: f64,
+    /// Whether to collect hardware performance counters.
+    ///
+    /// This is only supported by the native runner.
+    pub perf_counters: bool,
+    /// If set, interleave the runs of every benchmark in an order shuffled
+    /// with the given seed.
+    ///
+    /// This is only supported by the native runner.
+    pub interleave: Option<u64>,
+    /// The state of the page cache for the inputs at the start of each run.
+    pub cache: CacheMode,
+    /// The longest a single request may take before the participant is
+    /// stopped, if limited.
+    ///
+    /// This is only used by the warm runner, as a session could otherwise
+    /// hang forever.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for RunSettings {
+    fn default() -> Self {
+        Self {
+            warmup: 2,
+            min_runs: 3,
+            max_runs: 200,
+            min_time: 3.0,
+            perf_counters: false,
+            interleave: None,
+            cache: CacheMode::Warm,
+            timeout: None,
+        }
+    }
+}
+
+/// The results of running benchmarks.
+#[derive(Debug, Default, Clone)]
+pub struct RunResults {
+    /// The summary for each participant and input.
+    pub rows: Vec<BenchCSVRow>,
+    /// The raw samples for each participant and input.
+    pub samples: Vec<BenchSamples>,
+    /// The runner's own markdown table of the results, if it produces one.
+    pub markdown: Option<String>,
+}
+
+impl Runner {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Hyperfine => "hyperfine",
+            Self::Native => "native",
+            Self::Warm => "warm",
+        }
+    }
+
+    /// Benchmark every candidate against every input, producing a row and a
+    /// set of samples for each combination.
+    ///
+    /// The native runner additionally times each part on its own for
+    /// candidates that support `AOC_PART`.
+    ///
+    /// The inputs are the names of files in the given `day_directory`.
+    pub fn run(
+        &self,
+        year: usize,
+        day: usize,
+        day_directory: &Path,
+        inputs: &[String],
+        candidates: &[(&String, &AocProject)],
+        settings: &RunSettings,
+    ) -> Result<RunResults> {
+        match self {
+            Self::Hyperfine => {
+                hyperfine::run(year, day, day_directory, inputs, candidates, settings)
+            }
+            Self::Native => native::run(year, day, day_directory, inputs, candidates, settings),
+            Self::Warm => warm::run(year, day, day_directory, inputs, candidates, settings),
+        }
+    }
+}
+
+/// Measure the startup overhead of each candidate, returning the mean startup
+/// time in seconds by participant name.
+///
+/// This always uses the native runner, regardless of the runner used for the
+/// benchmarks themselves.
+pub fn measure_startup(
+    year: usize,
+    day: usize,
+    day_directory: &Path,
+    input: &Path,
+    candidates: &[(&String, &AocProject)],
+    settings: &RunSettings,
+) -> Result<HashMap<String, f64>> {
+    native::startup(year, day, day_directory, input, candidates, settings)
+}
+
+/// Evict the given file from the page cache.
+///
+/// Only clean pages are evicted, which is all of them for an input that is
+/// never written to.
+#[cfg(target_os = "linux")]
+pub fn evict_from_cache(path: &Path) -> Result<()> {
+    use std::os::fd::AsRawFd;
+
+    use anyhow::Context;
+
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open '{}' for eviction", path.display()))?;
+
+    // SAFETY: the file descriptor is valid for the duration of the call.
+    let ret = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+
+    if ret != 0 {
+        return Err(std::io::Error::from_raw_os_error(ret))
+            .with_context(|| format!("Failed to evict '{}' from the page cache", path.display()));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn evict_from_cache(_path: &Path) -> Result<()> {
+    anyhow::bail!("Evicting inputs from the page cache is only supported on Linux")
+}
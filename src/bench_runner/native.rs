@@ -0,0 +1,455 @@
+use std::{
+    collections::HashMap,
+    os::unix::process::{CommandExt, ExitStatusExt},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    time::Instant,
+};
+
+use anyhow::{Context, Result, bail};
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+use crate::{
+    aoc_project::AocProject,
+    bench_data::{BenchCSVRow, BenchSamples, Sample},
+    failure, highlight,
+    limits::Limits,
+    stats,
+};
+
+use super::{CacheMode, RunResults, RunSettings, Runner, evict_from_cache, perf::PerfCounters};
+
+pub fn run(
+    year: usize,
+    day: usize,
+    day_directory: &Path,
+    inputs: &[String],
+    candidates: &[(&String, &AocProject)],
+    settings: &RunSettings,
+) -> Result<RunResults> {
+    let perf = if settings.perf_counters {
+        match PerfCounters::probe() {
+            Ok(perf) => {
+                println!(
+                    "  Collecting performance counters: {}",
+                    perf.event_names().join(", ")
+                );
+                Some(perf)
+            }
+            Err(reason) => {
+                println!(
+                    "  {}",
+                    highlight!(format!(
+                        "Performance counters are unavailable: {}. Continuing without them.",
+                        reason
+                    ))
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let floor = rss_floor()?;
+    println!(
+        "  Peak RSS at or below {:.1} MiB is the runner's floor and cannot be measured more precisely",
+        floor as f64 / (1024.0 * 1024.0)
+    );
+
+    let mut jobs = Vec::with_capacity(candidates.len() * inputs.len());
+    for (name, project) in candidates.iter() {
+        // every candidate runs both parts, and those that support it also run
+        // each part on its own
+        let parts: &[Option<u8>] = if project.supports_parts() {
+            &[None, Some(1), Some(2)]
+        } else {
+            &[None]
+        };
+
+        for input in inputs.iter() {
+            for part in parts.iter() {
+                jobs.push(Job {
+                    name,
+                    project,
+                    input,
+                    input_path: day_directory.join(input).canonicalize()?,
+                    noop: false,
+                    part: *part,
+                    samples: Vec::default(),
+                    target: 0,
+                    error: None,
+                });
+            }
+        }
+    }
+
+    let env = JobEnv {
+        year,
+        day,
+        day_directory,
+        settings,
+        perf: perf.as_ref(),
+    };
+
+    if let Some(seed) = settings.interleave {
+        println!("  Interleaving runs in shuffled order (seed: {})", seed);
+        run_interleaved(&env, &mut jobs, seed);
+    } else {
+        for job in jobs.iter_mut() {
+            if let Err(e) = run_sequential(&env, job) {
+                job.error = Some(e);
+            }
+        }
+    }
+
+    let mut results = RunResults::default();
+
+    for job in jobs {
+        // one participant failing shouldn't prevent everyone else from being
+        // benchmarked
+        if let Some(ref e) = job.error {
+            println!(
+                "  {}",
+                failure!(format!("{} | {}: {:#}", job.name, job.label(), e))
+            );
+            continue;
+        }
+
+        let mut row = BenchCSVRow::from_samples(
+            &job.samples,
+            year,
+            day,
+            job.name.to_string(),
+            job.input.to_string(),
+            job.project.language().to_string(),
+        );
+        row.part = job.part;
+        let peak_rss = row.peak_rss_max.unwrap_or_default();
+        println!(
+            "  {} | {}: {:.1} ms ± {:.1} ms, {}{:.1} MiB peak RSS ({} runs)",
+            job.name,
+            job.label(),
+            row.mean * 1000.0,
+            row.stddev * 1000.0,
+            if peak_rss <= floor { "≤ " } else { "" },
+            peak_rss as f64 / (1024.0 * 1024.0),
+            job.samples.len()
+        );
+        results.rows.push(row);
+        results.samples.push(BenchSamples {
+            participant: job.name.to_string(),
+            input: job.input.to_string(),
+            runner: Runner::Native.name().to_string(),
+            part: job.part,
+            cache: CacheMode::default(),
+            times: job.samples.iter().map(|s| s.wall).collect(),
+        });
+    }
+
+    Ok(results)
+}
+
+/// Measure the mean startup time in seconds of each candidate.
+///
+/// Candidates that support `AOC_NOOP` are run with it set and the given
+/// `input`, while everyone else is run with an empty input. Candidates that
+/// fail are omitted from the result.
+pub fn startup(
+    year: usize,
+    day: usize,
+    day_directory: &Path,
+    input: &Path,
+    candidates: &[(&String, &AocProject)],
+    settings: &RunSettings,
+) -> Result<HashMap<String, f64>> {
+    // the name must not be predictable, as participants could otherwise
+    // replace the file, and it is removed when dropped
+    let empty = tempfile::Builder::new()
+        .prefix("aoc-tools-empty-input-")
+        .tempfile()
+        .context("Failed to create empty input")?;
+
+    let env = JobEnv {
+        year,
+        day,
+        day_directory,
+        settings,
+        perf: None,
+    };
+
+    let mut out = HashMap::default();
+
+    for (name, project) in candidates.iter() {
+        let mut job = Job {
+            name,
+            project,
+            input: "",
+            input_path: if project.supports_noop() {
+                input.to_path_buf()
+            } else {
+                empty.path().to_path_buf()
+            },
+            noop: project.supports_noop(),
+            part: None,
+            samples: Vec::default(),
+            target: 0,
+            error: None,
+        };
+
+        match run_sequential(&env, &mut job) {
+            Ok(_) => {
+                let mean = stats::mean(&job.samples.iter().map(|s| s.wall).collect::<Vec<_>>());
+                println!("  {} | startup: {:.1} ms", name, mean * 1000.0);
+                out.insert(name.to_string(), mean);
+            }
+            Err(e) => {
+                println!(
+                    "  {}",
+                    highlight!(format!("{} | could not measure startup: {:#}", name, e))
+                );
+            }
+        }
+    }
+
+    empty.close().context("Failed to remove empty input")?;
+
+    Ok(out)
+}
+
+/// The shared context for running jobs.
+struct JobEnv<'a> {
+    year: usize,
+    day: usize,
+    day_directory: &'a Path,
+    settings: &'a RunSettings,
+    perf: Option<&'a PerfCounters>,
+}
+
+/// A single participant and input combination to benchmark.
+struct Job<'a> {
+    name: &'a str,
+    project: &'a AocProject,
+    input: &'a str,
+    input_path: PathBuf,
+    /// Whether to set `AOC_NOOP` for each run.
+    noop: bool,
+    /// The part to run on its own via `AOC_PART`, if any.
+    part: Option<u8>,
+    samples: Vec<Sample>,
+    /// The number of samples to collect, determined after the first sample.
+    target: usize,
+    error: Option<anyhow::Error>,
+}
+
+impl Job<'_> {
+    fn measure(&self, env: &JobEnv) -> Result<Sample> {
+        let mut cmd = self
+            .project
+            .bench_command(env.year, env.day, &self.input_path)?;
+        cmd.current_dir(env.day_directory);
+        if self.noop {
+            cmd.env("AOC_NOOP", "true");
+        }
+        if let Some(part) = self.part {
+            cmd.env("AOC_PART", part.to_string());
+        }
+        if env.settings.cache == CacheMode::Cold {
+            evict_from_cache(&self.input_path)?;
+        }
+        measure(&mut cmd, env.perf, self.project.limits())
+    }
+
+    /// The input, and the part if only one part is run.
+    fn label(&self) -> String {
+        match self.part {
+            Some(part) => format!("{} (part {})", self.input, part),
+            None => self.input.to_string(),
+        }
+    }
+
+    fn warmup(&self, env: &JobEnv) -> Result<()> {
+        for _ in 0..env.settings.warmup {
+            self.measure(env)?;
+        }
+
+        Ok(())
+    }
+
+    /// Take a sample, setting the target number of samples if this is the
+    /// first one.
+    ///
+    /// The target is determined by the duration of the first measured run,
+    /// such that the total time spent is roughly `min_time`, bounded by
+    /// `min_runs` and `max_runs`.
+    fn sample(&mut self, env: &JobEnv) -> Result<()> {
+        let sample = self.measure(env)?;
+
+        if self.samples.is_empty() {
+            let min_runs = env.settings.min_runs.max(1);
+            let max_runs = env.settings.max_runs.max(min_runs);
+            self.target = if sample.wall > 0.0 {
+                ((env.settings.min_time / sample.wall) as usize).clamp(min_runs, max_runs)
+            } else {
+                max_runs
+            };
+        }
+
+        self.samples.push(sample);
+
+        Ok(())
+    }
+
+    fn is_done(&self) -> bool {
+        self.error.is_some() || (!self.samples.is_empty() && self.samples.len() >= self.target)
+    }
+}
+
+/// Run all of the job's runs back-to-back.
+fn run_sequential(env: &JobEnv, job: &mut Job) -> Result<()> {
+    job.warmup(env)?;
+
+    while !job.is_done() {
+        job.sample(env)?;
+    }
+
+    Ok(())
+}
+
+/// Run the jobs in rounds, where each round runs every unfinished job once in
+/// a shuffled order. This avoids any one job consistently running first, or
+/// on a cooler CPU.
+fn run_interleaved(env: &JobEnv, jobs: &mut [Job], seed: u64) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut order: Vec<usize> = (0..jobs.len()).collect();
+
+    order.shuffle(&mut rng);
+    for idx in order.iter() {
+        if let Err(e) = jobs[*idx].warmup(env) {
+            jobs[*idx].error = Some(e);
+        }
+    }
+
+    loop {
+        let mut remaining: Vec<usize> = (0..jobs.len()).filter(|i| !jobs[*i].is_done()).collect();
+
+        if remaining.is_empty() {
+            break;
+        }
+
+        remaining.shuffle(&mut rng);
+        for idx in remaining {
+            if let Err(e) = jobs[idx].sample(env) {
+                jobs[idx].error = Some(e);
+            }
+        }
+    }
+}
+
+/// Run the given command to completion, measuring the wall time as well as the
+/// user time, system time, and peak memory usage of the reaped process.
+///
+/// If `perf` is specified, the available performance counters are collected as
+/// well. A command stopped by one of the `limits` fails with
+/// [LimitExceeded](crate::limits::LimitExceeded).
+pub fn measure(cmd: &mut Command, perf: Option<&PerfCounters>, limits: &Limits) -> Result<Sample> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    let start = Instant::now();
+    let (child, attached) = match perf {
+        Some(perf) => {
+            let (child, attached) = perf
+                .attach(cmd, |cmd| cmd.spawn())
+                .context("Failed to spawn benchmark command")?;
+            (child, Some(attached))
+        }
+        None => {
+            // At exec, the kernel folds the peak RSS of the address space
+            // being replaced into the child's `ru_maxrss`, so every measurement
+            // has a floor set by the runner (see [rss_floor]). With
+            // posix_spawn/vfork that address space is ours, with our peak RSS,
+            // whereas a regular fork only copies our current RSS. Registering a
+            // hook forces a regular fork to keep the floor as low as possible.
+            //
+            // SAFETY: the hook does nothing.
+            unsafe {
+                cmd.pre_exec(|| Ok(()));
+            }
+
+            (
+                cmd.spawn().context("Failed to spawn benchmark command")?,
+                None,
+            )
+        }
+    };
+    let pid = child.id() as libc::pid_t;
+
+    let mut status: libc::c_int = 0;
+    // SAFETY: rusage is a plain C struct for which all zeroes is valid.
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+
+    loop {
+        // SAFETY: the pointers are valid for the duration of the call, and the
+        // pid belongs to a child we have not yet waited on.
+        let ret = unsafe { libc::wait4(pid, &mut status, 0, &mut usage) };
+        if ret >= 0 {
+            break;
+        }
+
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err).context("Failed to wait for benchmark command");
+        }
+    }
+
+    let wall = start.elapsed().as_secs_f64();
+
+    let status = ExitStatus::from_raw(status);
+    if !status.success() {
+        // stderr is not captured, so only limits enforced by signals are seen
+        if let Some(exceeded) = limits.exceeded(&status, &[]) {
+            return Err(exceeded.into());
+        }
+
+        bail!("Benchmark command did not exit successfully");
+    }
+
+    Ok(Sample {
+        wall,
+        user: timeval_to_secs(&usage.ru_utime),
+        system: timeval_to_secs(&usage.ru_stime),
+        max_rss: max_rss_bytes(&usage),
+        counters: attached.map(|a| a.read()).unwrap_or_default(),
+    })
+}
+
+/// The lowest peak RSS in bytes that [measure] can report, measured by running
+/// `true`.
+///
+/// The floor is the runner's own RSS at the time of the fork, which the kernel
+/// attributes to the child. Peaks above the floor are exact, while a peak at
+/// the floor only means that the process used at most that much.
+pub fn rss_floor() -> Result<u64> {
+    let sample = measure(&mut Command::new("true"), None, &Limits::default())
+        .context("Failed to measure the peak RSS floor")?;
+
+    Ok(sample.max_rss)
+}
+
+/// The peak resident set size of the reaped process in bytes.
+///
+/// Linux reports `ru_maxrss` in kilobytes, while macOS reports it in bytes.
+fn max_rss_bytes(usage: &libc::rusage) -> u64 {
+    let raw = usage.ru_maxrss.max(0) as u64;
+    if cfg!(target_os = "macos") {
+        raw
+    } else {
+        raw * 1024
+    }
+}
+
+fn timeval_to_secs(tv: &libc::timeval) -> f64 {
+    tv.tv_sec as f64 + tv.tv_usec as f64 / 1_000_000.0
+}
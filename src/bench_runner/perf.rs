@@ -0,0 +1,426 @@
+//! Hardware performance counters via `perf_event_open`.
+//!
+//! Counters are attached to the benchmarked process before it calls `exec`, so
+//! that only the participant's code (and any processes it spawns) is counted.
+//! Because std only returns from `spawn` once the child has called `exec`, the
+//! child reports its pid over a pipe from a `pre_exec` hook and then waits for
+//! a helper thread in the parent to open the counters before continuing.
+//!
+//! Only user-space events are counted, which is permitted for a process's own
+//! children with the default `perf_event_paranoid` setting of 2.
+use std::process::Command;
+
+use crate::bench_data::Counters;
+#[cfg(target_os = "linux")]
+use crate::highlight;
+
+/// The hardware events we know how to count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Instructions,
+    Cycles,
+    CacheMisses,
+    BranchMisses,
+}
+
+impl Event {
+    const ALL: [Self; 4] = [
+        Self::Instructions,
+        Self::Cycles,
+        Self::CacheMisses,
+        Self::BranchMisses,
+    ];
+
+    #[cfg(target_os = "linux")]
+    fn config(&self) -> u64 {
+        // PERF_COUNT_HW_*
+        match self {
+            Self::Cycles => 0,
+            Self::Instructions => 1,
+            Self::CacheMisses => 3,
+            Self::BranchMisses => 5,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Instructions => "instructions",
+            Self::Cycles => "cycles",
+            Self::CacheMisses => "cache misses",
+            Self::BranchMisses => "branch misses",
+        }
+    }
+}
+
+/// The set of performance counters that are available on this host.
+#[derive(Debug, Clone)]
+pub struct PerfCounters {
+    events: Vec<Event>,
+}
+
+/// Counters that have been attached to a spawned process.
+pub struct Attached {
+    #[cfg(target_os = "linux")]
+    fds: Vec<(Event, std::os::fd::OwnedFd)>,
+}
+
+impl PerfCounters {
+    /// Determine which counters can be opened, returning a description of why
+    /// if none of them can.
+    ///
+    /// Individual events that are unsupported are reported but not treated as
+    /// an error as long as at least one event is available.
+    #[cfg(target_os = "linux")]
+    pub fn probe() -> Result<Self, String> {
+        let mut events = Vec::with_capacity(Event::ALL.len());
+        let mut last_err = None;
+
+        for event in Event::ALL {
+            match linux::open(event, 0, false) {
+                Ok(_) => events.push(event),
+                Err(e) => {
+                    println!(
+                        "  {}",
+                        highlight!(format!(
+                            "Counter for {} is unavailable: {}",
+                            event.name(),
+                            linux::explain(&e)
+                        ))
+                    );
+                    last_err = Some(e);
+                }
+            }
+        }
+
+        if events.is_empty() {
+            return Err(last_err
+                .map(|e| linux::explain(&e))
+                .unwrap_or_else(|| "no counters could be opened".to_string()));
+        }
+
+        Ok(Self { events })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn probe() -> Result<Self, String> {
+        Err("hardware performance counters are only supported on Linux".to_string())
+    }
+
+    pub fn event_names(&self) -> Vec<&'static str> {
+        self.events.iter().map(|e| e.name()).collect()
+    }
+
+    /// Arrange for the counters to be attached to the process spawned by
+    /// `spawn`, which is given the command after it has been configured.
+    #[cfg(target_os = "linux")]
+    pub fn attach<F, T>(&self, cmd: &mut Command, spawn: F) -> std::io::Result<(T, Attached)>
+    where
+        F: FnOnce(&mut Command) -> std::io::Result<T>,
+    {
+        linux::attach(&self.events, cmd, spawn)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn attach<F, T>(&self, _cmd: &mut Command, _spawn: F) -> std::io::Result<(T, Attached)>
+    where
+        F: FnOnce(&mut Command) -> std::io::Result<T>,
+    {
+        Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+    }
+}
+
+impl Attached {
+    /// Read the counter values. This should be called after the process has
+    /// been reaped.
+    #[cfg(target_os = "linux")]
+    pub fn read(self) -> Counters {
+        let mut out = Counters::default();
+
+        for (event, fd) in self.fds.iter() {
+            let value = linux::read(fd).ok();
+            match event {
+                Event::Instructions => out.instructions = value,
+                Event::Cycles => out.cycles = value,
+                Event::CacheMisses => out.cache_misses = value,
+                Event::BranchMisses => out.branch_misses = value,
+            }
+        }
+
+        out
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn read(self) -> Counters {
+        Counters::default()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{
+        io,
+        os::{
+            fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+            unix::process::CommandExt,
+        },
+        process::Command,
+    };
+
+    use super::{Attached, Event};
+
+    const PERF_TYPE_HARDWARE: u32 = 0;
+    const PERF_FORMAT_TOTAL_TIME_ENABLED: u64 = 1 << 0;
+    const PERF_FORMAT_TOTAL_TIME_RUNNING: u64 = 1 << 1;
+    const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+
+    const FLAG_DISABLED: u64 = 1 << 0;
+    const FLAG_INHERIT: u64 = 1 << 1;
+    const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+    const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+    const FLAG_ENABLE_ON_EXEC: u64 = 1 << 12;
+
+    /// `struct perf_event_attr`, as of PERF_ATTR_SIZE_VER5.
+    ///
+    /// The bitfield following `read_format` is represented as `flags`.
+    #[repr(C)]
+    #[derive(Debug, Default)]
+    pub(super) struct PerfEventAttr {
+        type_: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        config1: u64,
+        config2: u64,
+        branch_sample_type: u64,
+        sample_regs_user: u64,
+        sample_stack_user: u32,
+        clockid: i32,
+        sample_regs_intr: u64,
+        aux_watermark: u32,
+        sample_max_stack: u16,
+        reserved_2: u16,
+    }
+
+    /// Open a counter for the given pid (0 for the calling thread).
+    ///
+    /// If `on_exec` is set, the counter is enabled when the process calls
+    /// `exec`, otherwise it is left disabled.
+    pub fn open(event: Event, pid: libc::pid_t, on_exec: bool) -> io::Result<OwnedFd> {
+        let mut flags = FLAG_DISABLED | FLAG_INHERIT | FLAG_EXCLUDE_KERNEL | FLAG_EXCLUDE_HV;
+        if on_exec {
+            flags |= FLAG_ENABLE_ON_EXEC;
+        }
+
+        let attr = PerfEventAttr {
+            type_: PERF_TYPE_HARDWARE,
+            size: std::mem::size_of::<PerfEventAttr>() as u32,
+            config: event.config(),
+            read_format: PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING,
+            flags,
+            ..Default::default()
+        };
+
+        // SAFETY: attr is a valid perf_event_attr that outlives the call.
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open,
+                &attr as *const PerfEventAttr,
+                pid,
+                -1 as libc::c_int,
+                -1 as libc::c_int,
+                PERF_FLAG_FD_CLOEXEC,
+            )
+        };
+
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // SAFETY: the syscall succeeded, so this is a new fd that we own.
+        Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+    }
+
+    /// Read a counter, scaling the value if the counter was multiplexed.
+    pub fn read(fd: &OwnedFd) -> io::Result<u64> {
+        // value, time enabled, time running
+        let mut buf = [0u64; 3];
+        // SAFETY: buf is valid for writes of its full size.
+        let ret = unsafe {
+            libc::read(
+                fd.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                std::mem::size_of_val(&buf),
+            )
+        };
+
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let [value, enabled, running] = buf;
+        if running == 0 {
+            return Err(io::Error::other("counter never ran"));
+        }
+
+        if running < enabled {
+            Ok((value as f64 * enabled as f64 / running as f64) as u64)
+        } else {
+            Ok(value)
+        }
+    }
+
+    pub fn explain(err: &io::Error) -> String {
+        match err.raw_os_error() {
+            Some(libc::EACCES) | Some(libc::EPERM) => {
+                let paranoid = std::fs::read_to_string("/proc/sys/kernel/perf_event_paranoid")
+                    .map(|s| s.trim().to_string())
+                    .unwrap_or_else(|_| "unknown".to_string());
+                format!(
+                    "permission denied (perf_event_paranoid is {}, must be 2 or lower)",
+                    paranoid
+                )
+            }
+            Some(libc::ENOENT) | Some(libc::EOPNOTSUPP) => {
+                "the event is not supported by this CPU or hypervisor".to_string()
+            }
+            Some(libc::ENOSYS) => "perf_event_open is not supported by this kernel".to_string(),
+            _ => err.to_string(),
+        }
+    }
+
+    fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+        let mut fds = [0 as libc::c_int; 2];
+        // SAFETY: fds is valid for writes of two ints.
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // SAFETY: pipe2 succeeded, so these are new fds that we own.
+        Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+    }
+
+    pub fn attach<F, T>(events: &[Event], cmd: &mut Command, spawn: F) -> io::Result<(T, Attached)>
+    where
+        F: FnOnce(&mut Command) -> io::Result<T>,
+    {
+        // the child sends its pid on the first pipe, then waits for a byte on
+        // the second before continuing on to exec
+        let (pid_read, pid_write) = pipe()?;
+        let (go_read, go_write) = pipe()?;
+
+        let child_pid_write = pid_write.as_raw_fd();
+        let child_go_read = go_read.as_raw_fd();
+
+        // SAFETY: the hook only makes async-signal-safe calls.
+        unsafe {
+            cmd.pre_exec(move || {
+                let pid = libc::getpid();
+                let bytes = pid.to_ne_bytes();
+                if libc::write(
+                    child_pid_write,
+                    bytes.as_ptr() as *const libc::c_void,
+                    bytes.len(),
+                ) != bytes.len() as isize
+                {
+                    return Err(io::Error::last_os_error());
+                }
+
+                let mut go = 0u8;
+                if libc::read(child_go_read, &mut go as *mut u8 as *mut libc::c_void, 1) != 1 {
+                    return Err(io::Error::last_os_error());
+                }
+
+                Ok(())
+            });
+        }
+
+        let events = events.to_vec();
+        let helper = std::thread::spawn(move || -> Vec<(Event, OwnedFd)> {
+            let mut bytes = [0u8; std::mem::size_of::<libc::pid_t>()];
+            // SAFETY: bytes is valid for writes of its full size.
+            let ret = unsafe {
+                libc::read(
+                    pid_read.as_raw_fd(),
+                    bytes.as_mut_ptr() as *mut libc::c_void,
+                    bytes.len(),
+                )
+            };
+
+            if ret != bytes.len() as isize {
+                // the child was never started
+                return Vec::default();
+            }
+
+            // any counters that fail to open are simply not reported
+            let pid = libc::pid_t::from_ne_bytes(bytes);
+            let fds = events
+                .iter()
+                .filter_map(|e| open(*e, pid, true).ok().map(|fd| (*e, fd)))
+                .collect();
+
+            // always let the child continue, even if we failed
+            let go = 1u8;
+            // SAFETY: go is valid for reads of one byte.
+            unsafe {
+                libc::write(
+                    go_write.as_raw_fd(),
+                    &go as *const u8 as *const libc::c_void,
+                    1,
+                );
+            }
+
+            fds
+        });
+
+        let spawned = spawn(cmd);
+
+        // The child has exec'd or failed by now, so we can close our copies of
+        // its ends, which unblocks the helper if the child never started.
+        drop(pid_write);
+        drop(go_read);
+
+        let fds = helper.join().unwrap_or_default();
+
+        Ok((spawned?, Attached { fds }))
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attr_layout() {
+        // PERF_ATTR_SIZE_VER5
+        assert_eq!(std::mem::size_of::<linux::PerfEventAttr>(), 112);
+        assert_eq!(std::mem::align_of::<linux::PerfEventAttr>(), 8);
+    }
+
+    #[test]
+    fn count_child() {
+        // counters are commonly unavailable in containers and VMs
+        let perf = match PerfCounters::probe() {
+            Ok(perf) => perf,
+            Err(reason) => {
+                println!("skipping, performance counters are unavailable: {}", reason);
+                return;
+            }
+        };
+
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg("i=0; while [ $i -lt 1000 ]; do i=$((i + 1)); done");
+        let (mut child, attached) = perf.attach(&mut cmd, |cmd| cmd.spawn()).unwrap();
+        assert!(child.wait().unwrap().success());
+
+        let counters = attached.read();
+        if perf.events.contains(&Event::Instructions) {
+            assert!(counters.instructions.is_some_and(|i| i > 1000));
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::{path::Path, time::Instant};
+
+use anyhow::{Result, bail};
+
+use crate::{
+    aoc_project::AocProject,
+    bench_data::{BenchCSVRow, BenchSamples, Sample},
+    failure, highlight,
+    session::Session,
+    stats,
+};
+
+use super::{CacheMode, RunResults, RunSettings, Runner, evict_from_cache};
+
+/// Benchmark candidates through persistent sessions, which excludes the
+/// startup time of their processes from the results.
+///
+/// Each sample is the time between sending a request and receiving its
+/// response. Candidates that do not support sessions are skipped.
+pub fn run(
+    year: usize,
+    day: usize,
+    day_directory: &Path,
+    inputs: &[String],
+    candidates: &[(&String, &AocProject)],
+    settings: &RunSettings,
+) -> Result<RunResults> {
+    // there is no flag for memory, so the empty columns are always explained
+    println!(
+        "  {}",
+        highlight!(
+            "Peak memory usage is not measured by the warm runner. Use the native runner to measure it."
+        )
+    );
+
+    if settings.perf_counters {
+        println!(
+            "  {}",
+            highlight!("Performance counters are not supported by the warm runner. Ignoring.")
+        );
+    }
+
+    if settings.interleave.is_some() {
+        println!(
+            "  {}",
+            highlight!("Interleaving is not supported by the warm runner. Ignoring.")
+        );
+    }
+
+    if candidates.iter().any(|(_, p)| p.supports_parts()) {
+        println!(
+            "  {}",
+            highlight!("Timing individual parts is not supported by the warm runner. Ignoring.")
+        );
+    }
+
+    let mut results = RunResults::default();
+
+    for (name, project) in candidates.iter() {
+        if !project.supports_session() {
+            println!(
+                "  {}",
+                highlight!(format!("{} does not support sessions. Skipping.", name))
+            );
+            continue;
+        }
+
+        let mut session = match project.start_session(&[day_directory]) {
+            Ok(session) => session,
+            Err(e) => {
+                println!("  {}", failure!(format!("{}: {:#}", name, e)));
+                continue;
+            }
+        };
+
+        for input in inputs.iter() {
+            let input_path = day_directory.join(input).canonicalize()?;
+
+            let samples = match sample(&mut session, year, day, &input_path, settings) {
+                Ok(samples) => samples,
+                Err(e) => {
+                    // the session is unusable after a failure, so nothing else
+                    // can be run for this participant
+                    println!("  {}", failure!(format!("{} | {}: {:#}", name, input, e)));
+                    break;
+                }
+            };
+
+            let mut row = BenchCSVRow::from_samples(
+                &samples.measured,
+                year,
+                day,
+                name.to_string(),
+                input.to_string(),
+                project.language().to_string(),
+            );
+            // memory is not measured per request
+            row.peak_rss_mean = None;
+            row.peak_rss_max = None;
+
+            print!(
+                "  {} | {}: {:.1} ms ± {:.1} ms ({} runs)",
+                name,
+                input,
+                row.mean * 1000.0,
+                row.stddev * 1000.0,
+                samples.measured.len()
+            );
+            if !samples.reported.is_empty() {
+                print!(
+                    ", {:.1} ms reported",
+                    stats::mean(&samples.reported) * 1000.0
+                );
+            }
+            println!();
+
+            results.rows.push(row);
+            results.samples.push(BenchSamples {
+                participant: name.to_string(),
+                input: input.to_string(),
+                runner: Runner::Warm.name().to_string(),
+                part: None,
+                cache: CacheMode::default(),
+                times: samples.measured.iter().map(|s| s.wall).collect(),
+            });
+        }
+
+        if let Err(e) = session.finish(settings.timeout) {
+            println!("  {}", highlight!(format!("{}: {:#}", name, e)));
+        }
+    }
+
+    Ok(results)
+}
+
+/// The samples of a single input.
+struct Samples {
+    /// The measured round trip times.
+    measured: Vec<Sample>,
+    /// The total times reported by the participant, if any.
+    reported: Vec<f64>,
+}
+
+/// Warm up and then sample the given input until the total time spent is
+/// roughly `min_time`, bounded by `min_runs` and `max_runs`.
+fn sample(
+    session: &mut Session,
+    year: usize,
+    day: usize,
+    input: &Path,
+    settings: &RunSettings,
+) -> Result<Samples> {
+    let mut measure = || -> Result<(f64, Option<f64>)> {
+        if settings.cache == CacheMode::Cold {
+            evict_from_cache(input)?;
+        }
+
+        let start = Instant::now();
+        let response = session.solve(year, day, input, settings.timeout)?;
+        let wall = start.elapsed().as_secs_f64();
+
+        if response.solution.is_none() {
+            bail!("Day is not implemented");
+        }
+
+        Ok((wall, response.timings.and_then(|t| t.total)))
+    };
+
+    for _ in 0..settings.warmup {
+        measure()?;
+    }
+
+    let min_runs = settings.min_runs.max(1);
+    let max_runs = settings.max_runs.max(min_runs);
+    let mut target = max_runs;
+
+    let mut out = Samples {
+        measured: Vec::default(),
+        reported: Vec::default(),
+    };
+
+    while out.measured.len() < target {
+        let (wall, reported) = measure()?;
+
+        if out.measured.is_empty() && wall > 0.0 {
+            target = ((settings.min_time / wall) as usize).clamp(min_runs, max_runs);
+        }
+
+        out.measured.push(Sample {
+            wall,
+            ..Default::default()
+        });
+        out.reported.extend(reported);
+    }
+
+    Ok(out)
+}
@@ -1,22 +1,31 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::File,
     io::{BufWriter, Write},
     path::{Path, PathBuf},
-    process::Command,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Context, Result, bail};
 use clap::Args;
-use itertools::Itertools;
-use which::which;
 
 use crate::{
-    aoc_project::AocProject,
-    attention,
-    bench_data::{BenchCSVRow, OriginalCSVRow},
+    aoc_project::{AocProject, BuildStatus},
+    bench_data::{
+        BenchSamples, PARTICIPANTS, Participants, input_size, load_benches, markdown_table,
+        parts_markdown_table,
+    },
+    bench_runner::{CacheMode, RunResults, RunSettings, Runner, measure_startup},
     config::Config,
+    failure,
+    git::Revision,
     highlight,
+    host::{BenchEnv, pin_to_cpus},
+    input_selection::SelectedInputs,
+    inputs::{Manifest, PlainInputs, Problem},
+    limits::LimitExceeded,
+    regression,
+    sandbox::Backend,
     solution::Solutions,
     success,
     util::day_directory_name,
@@ -24,15 +33,16 @@ use crate::{
 
 /// Run comparative benchmarks for a given day between the config participants.
 ///
-/// This produces a files named benches.md and benches_raw.csv in the directory
-/// for the given day.
-///
-/// This requires hyperfine to be installed.
+/// This produces benches.md and benches_raw.csv in the directory for the given
+/// day, along with selected_inputs.json, bench_samples.json, bench_env.json,
+/// participants.json and revisions.json. Previous results are archived under
+/// `history/` and compared with the new ones in regressions.md.
 ///
 /// This assumes that the configured projects have all passed the
 /// check-solutions command for the available inputs, implying that only inputs
 /// for which there is a solution will be used. A project will not be benched on
-/// a given day if its runtime exceeds the configured timeout.
+/// a given day if its runtime exceeds the configured timeout, and a project
+/// that fails to build or run is left out without affecting anyone else.
 #[derive(Debug, Clone, Args)]
 pub struct Bench {
     /// The year.
@@ -46,18 +56,164 @@ pub struct Bench {
     /// This assumes a `<day>_<padded number>` directory structure containing
     /// the inputs.
     inputs: PathBuf,
+
+    /// The seed to use when randomly selecting inputs.
+    ///
+    /// This overrides any seed specified in the config.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Only benchmark the specified participant(s).
+    ///
+    /// Their results replace their previous entries, while everyone else's are
+    /// left untouched. This re-uses the previously selected inputs, and so
+    /// requires a previous run of every participant with the same runner,
+    /// cache mode and startup measurement. May be specified multiple times.
+    #[arg(short, long = "participant")]
+    participants: Vec<String>,
+
+    /// The benchmark runner to use.
+    ///
+    /// Only the native runner times each part on its own, for participants
+    /// that set `supports_parts`, which produces a benches_parts.md. Runs of
+    /// every participant with hyperfine keep its own table in benches.md,
+    /// unless startup overhead is measured.
+    #[arg(long, value_enum, default_value_t = Runner::default())]
+    runner: Runner,
+
+    /// The number of warmup runs for each benchmark.
+    #[arg(long, default_value_t = 2)]
+    warmup: usize,
+
+    /// The minimum number of measured runs for each benchmark.
+    #[arg(long, default_value_t = 3)]
+    min_runs: usize,
+
+    /// The maximum number of measured runs for each benchmark.
+    #[arg(long, default_value_t = 200)]
+    max_runs: usize,
+
+    /// The target time in seconds to spend on each benchmark.
+    ///
+    /// The native runner uses this to pick the number of runs between the
+    /// minimum and maximum. hyperfine uses its own fixed target.
+    #[arg(long, default_value_t = 3.0)]
+    min_time: f64,
+
+    /// Collect hardware performance counters (instructions, cycles, cache and
+    /// branch misses) for each run.
+    ///
+    /// This is only supported by the native runner on Linux. If the counters
+    /// are unavailable, benchmarks are run without them.
+    #[arg(long)]
+    perf_counters: bool,
+
+    /// Interleave the runs of all participants and inputs in a shuffled order,
+    /// instead of running each benchmark back-to-back.
+    ///
+    /// This reduces the bias from always running the same participants first.
+    /// This is only supported by the native runner.
+    #[arg(long)]
+    interleave: bool,
+
+    /// The seed for shuffling interleaved runs.
+    ///
+    /// A seed is generated if not specified. The seed used by a run of every
+    /// participant is recorded in bench_env.json.
+    #[arg(long, requires = "interleave")]
+    interleave_seed: Option<u64>,
+
+    /// The significance level used when comparing against previous results.
+    #[arg(long, default_value_t = 0.05)]
+    alpha: f64,
+
+    /// Exit with an error if any participant is significantly slower than in
+    /// the previous results by more than this percentage.
+    #[arg(long)]
+    fail_on_regression: Option<f64>,
+
+    /// Separately measure the startup overhead of each participant.
+    ///
+    /// Participants with `supports_noop` set are run with the `AOC_NOOP`
+    /// environment variable, everyone else is run with an empty input. The
+    /// startup time and the mean adjusted by it are added to the results. This
+    /// always uses the native runner.
+    #[arg(long)]
+    startup: bool,
+
+    /// The state of the page cache for the inputs at the start of each run.
+    ///
+    /// In cold mode, the inputs are evicted from the page cache before every
+    /// run, which is only supported on Linux. The results are tagged with the
+    /// mode, and are only compared with previous results of the same mode.
+    #[arg(long, value_enum, default_value_t = CacheMode::default())]
+    cache: CacheMode,
+
+    /// Do not run the participants' `build_cmd` before benchmarking.
+    #[arg(long)]
+    skip_build: bool,
 }
 
 impl Bench {
+    fn settings(&self, config: &Config) -> RunSettings {
+        RunSettings {
+            warmup: self.warmup,
+            min_runs: self.min_runs,
+            max_runs: self.max_runs,
+            min_time: self.min_time,
+            perf_counters: self.perf_counters,
+            interleave: self
+                .interleave
+                .then(|| self.interleave_seed.unwrap_or_else(rand::random)),
+            cache: self.cache,
+            timeout: Some(Duration::from_secs(config.timeout() as u64)),
+        }
+    }
+
+    /// Refuse to mix the results of a subset run with previous results that
+    /// were measured differently.
+    fn check_previous_env(&self, env: &BenchEnv) -> Result<()> {
+        if env.runner != self.runner.name() {
+            bail!(
+                "The previous results were measured with the {} runner, not {}. Benchmark every participant to change it.",
+                env.runner,
+                self.runner.name()
+            );
+        }
+
+        if let Some(cache) = env.cache
+            && cache != self.cache
+        {
+            bail!(
+                "The previous results were measured with a {} cache, not {}. Benchmark every participant to change it.",
+                cache.as_str(),
+                self.cache.as_str()
+            );
+        }
+
+        if let Some(startup) = env.startup
+            && startup != self.startup
+        {
+            bail!(
+                "The previous results were measured {} startup overhead. Benchmark every participant to change it.",
+                if startup { "with" } else { "without" }
+            );
+        }
+
+        Ok(())
+    }
+
     pub fn run(&self, config: &Config) -> Result<()> {
-        if which("hyperfine").is_err() {
-            bail!("hyperfine must be installed");
+        for name in self.participants.iter() {
+            if !config.participants().contains_key(name) {
+                bail!("Participant does not exist: {}", name);
+            }
         }
 
         println!("> Day: {} benchmarking", self.day);
 
         let day_directory_name = day_directory_name(self.day);
-        let day_directory = self.inputs.join(day_directory_name);
+        let day_directory = self.inputs.join(&day_directory_name);
 
         if !day_directory.is_dir() {
             println!("> No inputs for day {}", self.day);
@@ -73,32 +229,105 @@ impl Bench {
 
         let solutions = Solutions::from_file(solution_file)?;
 
-        // get the first N official inputs
-        let inputs_raw: Vec<_> = solutions
-            .keys()
-            .filter(|n| n.starts_with("input-"))
-            .take(config.max_inputs())
-            .collect();
+        // encrypted inputs are decrypted, and everything that reads an input
+        // reads it from here
+        let plain = PlainInputs::open(&self.inputs, [self.day])?;
+        let input_directory = plain.path().join(&day_directory_name);
+
+        let manifest = Manifest::load(&day_directory)?;
+        let problems = manifest.verify(&input_directory)?;
+        for problem in problems.iter() {
+            println!("  {}", highlight!(problem.to_string()));
+        }
+        let is_modified = |name: &str| problems.contains(&Problem::Modified(name.to_string()));
+
+        let selection_file = day_directory.join("selected_inputs.json");
+
+        // When re-benching a subset of participants, we want to use the same
+        // inputs as everyone else, so we re-use the previous selection.
+        let selection = if !self.participants.is_empty() {
+            if !selection_file.is_file() {
+                bail!(
+                    "No previously selected inputs for day {}. Benchmark every participant first.",
+                    self.day
+                );
+            }
+
+            let raw = std::fs::read_to_string(&selection_file)
+                .context("Failed to read selected inputs")?;
+            let selection: SelectedInputs =
+                serde_json::from_str(&raw).context("Failed to parse selected inputs")?;
+
+            // the results of everyone else must have been measured the same
+            let env_file = day_directory.join("bench_env.json");
+            if env_file.is_file() {
+                self.check_previous_env(&BenchEnv::from_file(&env_file)?)?;
+            }
+
+            println!("> Re-using the previously selected inputs:");
+
+            selection
+        } else {
+            // select the official inputs according to the configured strategy,
+            // counting inputs shared by several participants only once, and
+            // skipping any that changed since they were copied
+            let available: Vec<_> = solutions
+                .keys()
+                .filter(|n| n.starts_with("input-") && !manifest.is_alias(n) && !is_modified(n))
+                .map(|n| n.as_str())
+                .collect();
+
+            if available.is_empty() {
+                bail!("unexpected: no official inputs in solutions file");
+            }
 
-        println!("> Using the selected inputs (max {}):", config.max_inputs());
-        for input in inputs_raw.iter() {
+            let selection = config
+                .input_selection()
+                .select(
+                    self.day,
+                    &available,
+                    &input_directory,
+                    config.max_inputs(),
+                    self.seed,
+                )
+                .context("Failed to select inputs")?;
+
+            println!(
+                "> Using the selected inputs (strategy: {}, max {}):",
+                selection.strategy,
+                config.max_inputs()
+            );
+
+            selection
+        };
+
+        if let Some(seed) = selection.seed {
+            println!("  seed: {}", seed);
+        }
+        for input in selection.inputs.iter() {
             println!("    {}", input);
         }
         println!();
 
-        if inputs_raw.is_empty() {
-            bail!("unexpected: no official inputs in solutions file");
+        if selection.inputs.is_empty() {
+            bail!("No inputs were selected for benchmarking");
         }
 
-        let inputs = inputs_raw.iter().join(",");
+        if let Some(input) = selection.inputs.iter().find(|i| is_modified(i)) {
+            bail!("Selected input {} was modified after it was copied", input);
+        }
+
+        let inputs_raw = &selection.inputs;
 
         // we need to filter out the projects that will not solve the current
         // day by attempting to get a solution for any of the inputs
-        let canary = day_directory
-            .join(
-                solutions.keys().find(|n| n.starts_with("input-")).unwrap(), // unwrap is safe because we actually checked already
-            )
-            .canonicalize()?;
+        let canary = input_directory.join(&inputs_raw[0]).canonicalize()?;
+
+        if config.participants().iter().any(|(n, p)| {
+            p.sandbox() && (self.participants.is_empty() || self.participants.contains(n))
+        }) {
+            Backend::report();
+        }
 
         // we attempt to solve all the participants' solutions for the given day
         // and the canary file, further requiring that they complete in under
@@ -106,17 +335,40 @@ impl Bench {
         let mut candidates: Vec<_> = config
             .participants()
             .iter()
-            .filter(|(_, p)| {
-                matches!(
-                    p.solve(self.year, self.day, &canary, Some(config.timeout())),
-                    Ok(Some(_))
-                )
-            })
+            .filter(|(n, _)| self.participants.is_empty() || self.participants.contains(n))
+            .filter(|(n, p)| self.skip_build || build(n, p))
+            .filter(
+                |(n, p)| match p.solve(self.year, self.day, &canary, Some(config.timeout())) {
+                    Ok(Some(_)) => true,
+                    Ok(None) => false,
+                    Err(e) => {
+                        // participants that fail outright are quietly excluded,
+                        // but hitting a limit is worth knowing about
+                        if let Some(exceeded) = e.downcast_ref::<LimitExceeded>() {
+                            println!("  {}", failure!(format!("{}: {}", n, exceeded)));
+                        }
+                        false
+                    }
+                },
+            )
             .collect();
 
         candidates.sort_by(|a, b| a.0.cmp(b.0));
 
-        if candidates.is_empty() {
+        // every result records the version of the code that produced it
+        let mut revisions: HashMap<&str, Option<Revision>> = HashMap::default();
+        for (name, project) in candidates.iter() {
+            let revision = match project.revision() {
+                Ok(revision) => revision,
+                Err(e) => {
+                    println!("  {}", highlight!(format!("{:#}", e)));
+                    None
+                }
+            };
+            revisions.insert(name.as_str(), revision);
+        }
+
+        if candidates.is_empty() && self.participants.is_empty() {
             println!(
                 "  {}",
                 highlight!("No participants solve the specified day")
@@ -124,148 +376,289 @@ impl Bench {
             return Ok(());
         }
 
-        let mut cmd = Command::new("hyperfine");
-        cmd.current_dir(&day_directory);
-        cmd.env("AOC_YEAR", self.year.to_string());
-        cmd.env("AOC_DAY", self.day.to_string());
-        cmd.env("AOC_CI", "true");
-        cmd.args([
-            // warmup 2 times
-            "-w",
-            "2",
-            // at least 3 runs
-            "-m",
-            "3",
-            // at most 100 runs
-            "-M",
-            "200",
-            // iterate for each input
-            "-L",
-            "input",
-            &inputs,
-            // sort by the execution time instead of order of specification
-            "--sort",
-            "mean-time",
-            // export in various formats
-            "--export-markdown",
-            "benches.md",
-            "--export-csv",
-            "benches_raw.csv",
-        ]);
-
-        println!("  Benchmarking the following projects:");
-        for (name, _) in candidates.iter() {
-            println!("  {}", success!(name));
-        }
+        let bench_csv = day_directory.join("benches_raw.csv");
+        let samples_file = day_directory.join("bench_samples.json");
 
-        for (_, project) in candidates.iter() {
-            cmd.arg(format!(
-                "AOC_INPUT={{input}} {}",
-                project.bench_entrypoint()
-            ));
-        }
+        // keep the previous results around for comparison and posterity
+        let mut previous_rows = Vec::default();
+        let mut previous_samples = Vec::default();
+        if bench_csv.is_file() {
+            load_benches(&bench_csv, &mut previous_rows)?;
+
+            if samples_file.is_file() {
+                previous_samples = BenchSamples::from_file(&samples_file)?;
+            }
 
-        let status = cmd.status().context("Failed to execute hyperfine")?;
-        if !status.success() {
-            bail!("hyperfine did not exit successfully");
+            let archive = archive_results(&day_directory)?;
+            println!("  Archived previous results to {}", archive.display());
         }
 
-        // write the list of participants for this day's benchmarks
-        let mut participants_record: Vec<_> = candidates.iter().map(|(name, _)| name).collect();
-        participants_record.sort();
-        let output = File::create(day_directory.join("participants.json"))
-            .context("Failed to create participants file")?;
+        let mut results = if candidates.is_empty() {
+            println!(
+                "  {}",
+                highlight!("None of the specified participants solve the specified day")
+            );
+            RunResults::default()
+        } else {
+            let cpus = config.bench_cpus();
+
+            let settings = self.settings(config);
+
+            // record the host environment so that changes in results can be
+            // correlated with changes in hardware, which a subset run must not
+            // do, as the results it keeps were measured in the previous one
+            if self.participants.is_empty() {
+                let mut env = BenchEnv::capture(self.year, self.day, self.runner.name(), cpus);
+                env.cache = Some(settings.cache);
+                env.startup = Some(self.startup);
+                // only the native runner interleaves runs
+                if self.runner == Runner::Native {
+                    env.interleave_seed = settings.interleave;
+                }
+                let output = File::create(day_directory.join("bench_env.json"))
+                    .context("Failed to create bench env file")?;
+                let mut writer = BufWriter::new(output);
+                serde_json::to_writer(&mut writer, &env).context("Failed to write bench env")?;
+                writer.flush()?;
+            }
+
+            // this is done after capturing the environment so that the
+            // number of cores reflects the host rather than the pinned set
+            if !cpus.is_empty() {
+                pin_to_cpus(cpus)?;
+                println!("  Pinned benchmarks to CPUs {:?}", cpus);
+            }
+
+            println!("  Benchmarking the following projects:");
+            for (name, _) in candidates.iter() {
+                println!("  {}", success!(name));
+            }
+
+            let mut results = self.runner.run(
+                self.year,
+                self.day,
+                &input_directory,
+                inputs_raw,
+                &candidates,
+                &settings,
+            )?;
+
+            for row in results.rows.iter_mut() {
+                row.cache = settings.cache;
+                row.set_revision(
+                    revisions
+                        .get(row.participant.as_str())
+                        .and_then(|r| r.as_ref()),
+                );
+            }
+            for samples in results.samples.iter_mut() {
+                samples.cache = settings.cache;
+            }
+
+            // throughput makes results comparable across inputs of different
+            // sizes
+            for input in inputs_raw.iter() {
+                let (bytes, lines) = input_size(input_directory.join(input))?;
+                for row in results.rows.iter_mut().filter(|r| &r.input == input) {
+                    row.set_input_size(bytes, lines);
+                }
+            }
+
+            if self.startup && self.runner == Runner::Warm {
+                println!(
+                    "  {}",
+                    highlight!("Startup is already excluded by the warm runner. Ignoring.")
+                );
+            } else if self.startup {
+                println!("  Measuring startup overhead");
+                let startup = measure_startup(
+                    self.year,
+                    self.day,
+                    &input_directory,
+                    &canary,
+                    &candidates,
+                    &settings,
+                )?;
+
+                for row in results.rows.iter_mut() {
+                    if let Some(time) = startup.get(&row.participant) {
+                        row.set_startup(*time);
+                    }
+                }
+            }
+
+            results
+        };
+
+        // record the selected inputs so this run can be reproduced
+        let output =
+            File::create(&selection_file).context("Failed to create selected inputs file")?;
         let mut writer = BufWriter::new(output);
-        serde_json::to_writer(&mut writer, &participants_record)
-            .context("Failed to write participants")?;
+        serde_json::to_writer(&mut writer, &selection)
+            .context("Failed to write selected inputs")?;
         writer.flush()?;
 
-        // Hyperfine's combinations of command names and inputs don't allow for
-        // what we want to do, so we're going to rewrite the contents of the
-        // generated bench markdown to be what we want. This is memory
-        // inefficient with all the string replacements, but it probably won't
-        // be too bad. If at some point hyperfine allows for us to do this in a
-        // better way, we can get rid of this code.
-        let mut bench_contents = std::fs::read_to_string(day_directory.join("benches.md"))?;
-
-        // we're going to change the header of the markdown table
-        bench_contents = bench_contents.replacen("Command", "Participant | Input", 1);
-        // and the alignment spec
-        bench_contents = bench_contents.replacen(":---", ":---|:---", 1);
-
-        // now we're going to replace the command name and add the inputs in a
-        // separate column.
-        for (name, project) in candidates.iter() {
-            for input_name in inputs_raw.iter() {
-                let needle = format!("`AOC_INPUT={} {}`", input_name, project.bench_entrypoint());
-                let replacement = format!("{} | {}", name, input_name);
-                bench_contents = bench_contents.replacen(&needle, &replacement, 1);
+        let comparisons = regression::compare(&previous_samples, &results.samples, self.alpha);
+
+        // only the participants that produced results were benchmarked, as
+        // runners may skip or drop some of the candidates
+        let benched_names: HashSet<String> =
+            results.rows.iter().map(|r| r.participant.clone()).collect();
+
+        let mut participants_record = Participants::default();
+        for name in benched_names.iter() {
+            participants_record.insert(
+                name.clone(),
+                revisions.get(name.as_str()).cloned().flatten(),
+            );
+        }
+
+        // merge with the existing results for everyone we did not bench,
+        // including specified participants that failed the canary
+        if !self.participants.is_empty() {
+            let benched = |name: &str| benched_names.contains(name);
+
+            if day_directory.join(PARTICIPANTS).is_file() {
+                let previous = Participants::load(&day_directory)?;
+                participants_record.extend(
+                    previous
+                        .iter()
+                        .filter(|(n, _)| !benched(n))
+                        .map(|(n, r)| (n.clone(), r.clone())),
+                );
             }
+
+            results.rows.extend(
+                previous_rows
+                    .into_iter()
+                    .filter(|r| !benched(&r.participant)),
+            );
+            results.samples.extend(
+                previous_samples
+                    .into_iter()
+                    .filter(|r| !benched(&r.participant)),
+            );
         }
 
-        // finally, write that file back to disk
+        // write the participants for this day's benchmarks
+        participants_record.save(&day_directory)?;
+
+        results.rows.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+
+        let mut writer = csv::Writer::from_path(&bench_csv)?;
+        for record in results.rows.iter() {
+            writer.serialize(record)?;
+        }
+        writer.flush()?;
+
+        let output = File::create(&samples_file).context("Failed to create samples file")?;
+        let mut writer = BufWriter::new(output);
+        serde_json::to_writer(&mut writer, &results.samples).context("Failed to write samples")?;
+        writer.flush()?;
+
+        // full hyperfine runs keep hyperfine's own table, while subset runs
+        // and extra metrics need the rows rendered ourselves
+        let table = match results.markdown {
+            Some(ref table) if self.participants.is_empty() && !self.startup => table.clone(),
+            _ => markdown_table(&results.rows),
+        };
         let mut bench_out = File::create(day_directory.join("benches.md"))?;
-        bench_out.write_all(bench_contents.as_bytes())?;
+        bench_out.write_all(table.as_bytes())?;
+
+        let parts_file = day_directory.join("benches_parts.md");
+        if let Some(table) = parts_markdown_table(&results.rows) {
+            let mut parts_out = File::create(&parts_file)?;
+            parts_out.write_all(table.as_bytes())?;
+        } else if parts_file.is_file() {
+            // don't leave a stale breakdown around
+            std::fs::remove_file(&parts_file).context("Failed to remove parts file")?;
+        }
 
-        // We want to do something similar for the raw csv
-        let bench_csv = day_directory.join("benches_raw.csv");
-        transform_bench_csv(bench_csv, self.year, self.day, &candidates)
-            .context("Could not transform bench csv")?;
+        let regressions_file = day_directory.join("regressions.md");
+        if !comparisons.is_empty() {
+            let table = regression::markdown_table(&comparisons);
+            println!("\n> Comparison with previous results:\n{}", table);
+
+            let mut out = File::create(&regressions_file)?;
+            out.write_all(table.as_bytes())?;
+        } else if regressions_file.is_file() {
+            // don't leave a comparison with older results around
+            std::fs::remove_file(&regressions_file).context("Failed to remove regressions file")?;
+        }
+
+        if let Some(threshold) = self.fail_on_regression {
+            let regressed: Vec<_> = comparisons
+                .iter()
+                .filter(|c| c.exceeds(threshold))
+                .map(|c| format!("{} ({})", c.participant, c.input))
+                .collect();
+
+            if !regressed.is_empty() {
+                bail!(
+                    "Regressions of more than {}% detected: {}",
+                    threshold,
+                    regressed.join(", ")
+                );
+            }
+        }
 
         Ok(())
     }
 }
 
-fn transform_bench_csv<P: AsRef<Path>>(
-    path: P,
-    year: usize,
-    day: usize,
-    candidates: &[(&String, &AocProject)],
-) -> Result<()> {
-    let path = path.as_ref();
-    let raw_bench_data: Vec<OriginalCSVRow> = {
-        let mut out = Vec::default();
-        let mut reader = csv::Reader::from_path(path)?;
-        for result in reader.deserialize() {
-            out.push(result?);
+/// Build the given participant, returning whether it is ready to be run.
+fn build(name: &str, project: &AocProject) -> bool {
+    match project.build() {
+        Ok(BuildStatus::Built) => {
+            println!("  {}", success!(format!("Built {}", name)));
+            true
         }
-        out
-    };
-
-    let lookup_map: HashMap<&str, (&str, &str)> = candidates
-        .iter()
-        .map(|(name, project)| {
-            (
-                project.bench_entrypoint(),
-                (name.as_str(), project.language()),
-            )
-        })
-        .collect();
-
-    let mut transformed = Vec::with_capacity(raw_bench_data.len());
-
-    for record in raw_bench_data {
-        let entrypoint = record.get_raw_command();
-        if let Some((name, language)) = lookup_map.get(&entrypoint.as_str()) {
-            transformed.push(BenchCSVRow::from_original(
-                record,
-                year,
-                day,
-                name.to_string(),
-                language.to_string(),
-            ));
-        } else {
+        Ok(BuildStatus::Cached) => {
+            println!("  {} is already built at its current commit", name);
+            true
+        }
+        Ok(BuildStatus::NotConfigured) => true,
+        Err(e) => {
             println!(
                 "  {}",
-                attention!(format!("Could not transform record for `{}`", &entrypoint))
+                failure!(format!("Failed to build {}: {:#}", name, e))
             );
+            false
         }
     }
+}
 
-    let mut writer = csv::Writer::from_path(path)?;
-    for record in transformed {
-        writer.serialize(record)?;
+/// The files that make up the results of a day's benchmarks.
+const RESULT_FILES: &[&str] = &[
+    "benches.md",
+    "benches_parts.md",
+    "benches_raw.csv",
+    "bench_samples.json",
+    "bench_env.json",
+    "participants.json",
+    "revisions.json",
+    "selected_inputs.json",
+    "regressions.md",
+];
+
+/// Copy the existing results into `history/<unix timestamp in ms>` in the
+/// given day directory, returning the path to the archive.
+fn archive_results(day_directory: &Path) -> Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+
+    let archive = day_directory.join("history").join(timestamp.to_string());
+    std::fs::create_dir_all(&archive).context("Failed to create archive directory")?;
+
+    for name in RESULT_FILES {
+        let path = day_directory.join(name);
+        if path.is_file() {
+            std::fs::copy(&path, archive.join(name))
+                .with_context(|| format!("Failed to archive {}", name))?;
+        }
     }
-    writer.flush()?;
-    Ok(())
+
+    Ok(archive)
 }
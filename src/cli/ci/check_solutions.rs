@@ -1,11 +1,19 @@
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use anyhow::{Context, Result, anyhow, bail};
 use clap::Args;
 
 use crate::{
+    aoc_project::{AocProject, BuildStatus},
     config::Config,
     failure, highlight,
+    inputs::{Manifest, PlainInputs, Problem},
+    limits::LimitExceeded,
+    sandbox::Backend,
+    session::Session,
     solution::{Solution, Solutions},
     success,
     util::day_directory_name,
@@ -17,6 +25,12 @@ use crate::{
 /// solve challenge inputs does not count as an overall failure when checking.
 ///
 /// This will timeout the checking after the specified timeout in the config.
+///
+/// If the participant has a `build_cmd`, it is run first, unless the project
+/// was already built at its current commit.
+///
+/// Participants that set `supports_session` are started once and sent every
+/// input over the session protocol, rather than being started for each input.
 #[derive(Debug, Clone, Args)]
 pub struct CheckSolutions {
     /// The particpatnt's solutions to check.
@@ -29,6 +43,10 @@ pub struct CheckSolutions {
     /// This assumes a `<day>_<padded number>` directory structure containing
     /// the inputs.
     inputs: PathBuf,
+
+    /// Do not run the participant's `build_cmd` before checking.
+    #[arg(long)]
+    skip_build: bool,
 }
 
 impl CheckSolutions {
@@ -42,12 +60,37 @@ impl CheckSolutions {
             .get(&self.participant)
             .ok_or_else(|| anyhow!("Particpant does not exist: {}", &self.participant))?;
 
+        if let Some(revision) = project.revision()? {
+            println!("Checking {} at {}", self.participant, revision);
+        }
+
+        if project.sandbox() {
+            Backend::report();
+        }
+
+        if !self.skip_build {
+            match project.build().context("Failed to build project")? {
+                BuildStatus::Built => println!("{}", success!("Built project")),
+                BuildStatus::Cached => println!("Project already built at its current commit"),
+                BuildStatus::NotConfigured => {}
+            }
+        }
+
         let year = config.year();
+        // the solver is only ever given decrypted inputs
+        let plain = PlainInputs::open(&self.inputs, 1..=config.days())?;
+        let mut solver = Solver::new(
+            project,
+            year,
+            config.timeout(),
+            plain.path().canonicalize()?,
+        );
 
         'days: for day in 1..=config.days() {
             println!();
             let day_directory_name = day_directory_name(day);
             let day_directory = self.inputs.join(&day_directory_name);
+            let input_directory = plain.path().join(&day_directory_name);
 
             if !day_directory.is_dir() {
                 println!("> No inputs for day {}", day);
@@ -70,13 +113,23 @@ impl CheckSolutions {
             // We're going to start with the official inputs, which have naming
             // format like `input-<name>`.
 
-            println!("> Day {}:\n  Checking official inputs", day);
+            println!("> Day {}:", day);
+
+            // inputs changed since they were copied no longer match their
+            // reference solutions, so they are not checked
+            let problems = Manifest::load(&day_directory)?.verify(&input_directory)?;
+            for problem in problems.iter() {
+                println!("  {}", highlight!(problem.to_string()));
+            }
+            let is_modified = |name: &String| problems.contains(&Problem::Modified(name.clone()));
+
+            println!("  Checking official inputs");
 
             for (input_name, solution) in solutions
                 .iter()
-                .filter(|(name, _)| name.starts_with("input-"))
+                .filter(|(name, _)| name.starts_with("input-") && !is_modified(name))
             {
-                let input_file = day_directory.join(input_name).canonicalize()?;
+                let input_file = input_directory.join(input_name).canonicalize()?;
 
                 // This would be unexpected but maybe not impossible. Let's skip
                 // if this ends up being the case
@@ -88,8 +141,8 @@ impl CheckSolutions {
                     continue;
                 }
 
-                if let Some(computed) = project
-                    .solve(year, day, &input_file, Some(config.timeout()))
+                if let Some(computed) = solver
+                    .solve(day, &input_file)
                     .context("Failed to produce solution")?
                 {
                     if !self.check_solution(day, config.days(), input_name, solution, &computed) {
@@ -109,9 +162,9 @@ impl CheckSolutions {
 
             for (input_name, solution) in solutions
                 .iter()
-                .filter(|(name, _)| name.starts_with("challenge-input-"))
+                .filter(|(name, _)| name.starts_with("challenge-input-") && !is_modified(name))
             {
-                let input_file = day_directory.join(input_name).canonicalize()?;
+                let input_file = input_directory.join(input_name).canonicalize()?;
 
                 // This would be unexpected but maybe not impossible. Let's skip
                 // if this ends up being the case
@@ -123,7 +176,7 @@ impl CheckSolutions {
                     continue;
                 }
 
-                match project.solve(year, day, &input_file, Some(config.timeout())) {
+                match solver.solve(day, &input_file) {
                     Ok(Some(computed)) => {
                         self.check_solution(day, config.days(), input_name, solution, &computed);
                     }
@@ -138,17 +191,22 @@ impl CheckSolutions {
                         );
                         continue 'days;
                     }
-                    Err(_) => {
-                        println!(
-                            "  {}",
-                            failure!("Project did not successfully produce a solution.")
-                        );
-                    }
+                    Err(e) => match e.downcast_ref::<LimitExceeded>() {
+                        Some(exceeded) => {
+                            println!("  {} {}", input_name, failure!(exceeded.to_string()));
+                        }
+                        None => {
+                            println!(
+                                "  {}",
+                                failure!("Project did not successfully produce a solution.")
+                            );
+                        }
+                    },
                 }
             }
         }
 
-        Ok(())
+        solver.finish()
     }
 
     fn check_solution(
@@ -177,3 +235,59 @@ impl CheckSolutions {
         }
     }
 }
+
+/// Solves inputs by either running the project once per input or, if the
+/// project supports it, through a single persistent session.
+struct Solver<'a> {
+    project: &'a AocProject,
+    year: usize,
+    timeout: usize,
+    /// The root directory of the inputs, which a sandboxed session can read.
+    inputs: PathBuf,
+    session: Option<Session>,
+}
+
+impl<'a> Solver<'a> {
+    fn new(project: &'a AocProject, year: usize, timeout: usize, inputs: PathBuf) -> Self {
+        Self {
+            project,
+            year,
+            timeout,
+            inputs,
+            session: None,
+        }
+    }
+
+    fn solve(&mut self, day: usize, input: &Path) -> Result<Option<Solution>> {
+        if !self.project.supports_session() {
+            return self
+                .project
+                .solve(self.year, day, input, Some(self.timeout));
+        }
+
+        let mut session = match self.session.take() {
+            Some(session) => session,
+            None => self.project.start_session(&[&self.inputs])?,
+        };
+
+        // a session that failed is dropped, killing it, and a new one is
+        // started for the next input
+        let response = session.solve(
+            self.year,
+            day,
+            input,
+            Some(Duration::from_secs(self.timeout as u64)),
+        )?;
+        self.session = Some(session);
+
+        Ok(response.solution)
+    }
+
+    fn finish(self) -> Result<()> {
+        if let Some(session) = self.session {
+            session.finish(Some(Duration::from_secs(self.timeout as u64)))?;
+        }
+
+        Ok(())
+    }
+}
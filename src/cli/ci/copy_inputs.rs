@@ -1,9 +1,22 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use anyhow::{Context, Result};
 use clap::Args;
 
-use crate::{config::Config, failure, highlight, success, util::day_directory_name};
+use crate::{
+    config::Config,
+    encryption::{self, Key, read_plain},
+    failure,
+    fetch::{self, Fetcher},
+    highlight,
+    inputs::{self, Alias, InputKind, InputRecord, Manifest},
+    success,
+    util::day_directory_name,
+};
 
 /// Copies the inputs for the configured participants to the specified location.
 ///
@@ -12,18 +25,97 @@ use crate::{config::Config, failure, highlight, success, util::day_directory_nam
 ///
 /// If inputs do not exist or cannot be gatherhed from a particular participant,
 /// those inputs are ignored.
+///
+/// The path reported by a participant must resolve to a regular file inside of
+/// their location, after following any symlinks, and must not be larger than
+/// the maximum size.
+///
+/// Inputs that are identical to an input that was already copied are not
+/// stored again. Instead, they are recorded as aliases of that input in the
+/// day's `manifest.json`, along with whose they are and where they came from,
+/// and are not solved or benchmarked separately.
+///
+/// With an anonymization salt, inputs are named with stable anonymous labels
+/// rather than usernames, so that the generated solutions and benchmarks do not
+/// reveal whose input is whose.
+///
+/// The manifest also records where every input came from, along with its
+/// SHA-256, which the other commands use to detect inputs that were changed or
+/// added after they were copied. The record of an input that has not changed
+/// since the last run is kept as it was, including when it was first copied,
+/// as is the record of a stored input that could not be copied again.
+///
+/// Participants with an `input_fetch` have their inputs fetched over HTTP with
+/// their session token instead of running their `input_cmd`. Fetched inputs are
+/// cached, so each one is only ever requested once, and requests are spaced
+/// out by at least the minimum interval. Fetched inputs that would be rejected
+/// are not cached.
+///
+/// Inputs that are not UTF-8 or that look like HTML error pages are rejected.
+/// With `--normalize`, inputs also have any byte order mark removed, CRLF line
+/// endings converted to LF, and end in exactly one newline.
 #[derive(Debug, Clone, Args)]
 pub struct CopyInputs {
     /// The root directory in which to put inputs.
     ///
     /// Inputs will be organized according to day under this directory.
     destination: PathBuf,
+
+    /// The maximum size of an input in bytes. Larger inputs are skipped.
+    #[arg(long, default_value_t = 10 * 1024 * 1024)]
+    max_input_size: u64,
+
+    /// Normalize the contents of inputs, reporting every change.
+    #[arg(long)]
+    normalize: bool,
+
+    /// Name inputs with anonymous labels derived from a hash of the
+    /// participant's username salted with this secret, instead of their
+    /// username.
+    ///
+    /// The labels are stable for a given salt, and only the manifest maps them
+    /// back to participants.
+    #[arg(long, env = inputs::SALT_VAR, hide_env_values = true)]
+    anonymize_salt: Option<String>,
+
+    /// Encrypt the stored inputs with a key derived from the secret in
+    /// `AOC_TOOLS_INPUT_KEY`.
+    ///
+    /// The other commands decrypt inputs into a temporary directory when they
+    /// need them, using the same variable. Inputs that are already stored
+    /// with the same contents and key are left as they are.
+    #[arg(long)]
+    encrypt: bool,
+
+    /// The directory to cache fetched inputs in.
+    ///
+    /// Defaults to `aoc-tools/inputs` in the user's cache directory.
+    #[arg(long)]
+    fetch_cache: Option<PathBuf>,
+
+    /// The minimum number of seconds between requests when fetching inputs.
+    #[arg(long, default_value_t = 1.0)]
+    fetch_interval: f64,
 }
 
 impl CopyInputs {
     pub fn run(&self, config: &Config) -> Result<()> {
         let year = config.year();
 
+        let key = if self.encrypt {
+            Some(Key::from_env().context("Cannot encrypt inputs")?)
+        } else {
+            None
+        };
+
+        let mut fetcher = Fetcher::new(
+            self.fetch_cache
+                .clone()
+                .unwrap_or_else(fetch::default_cache_dir),
+            Duration::try_from_secs_f64(self.fetch_interval).context("Invalid fetch interval")?,
+            self.max_input_size,
+        )?;
+
         for day in 1..=25 {
             println!();
             let day_directory_name = day_directory_name(day);
@@ -37,21 +129,110 @@ impl CopyInputs {
                 std::fs::create_dir(&day_directory).context("Failed to make directory")?;
             }
 
+            let previous = Manifest::load(&day_directory)?;
+            let mut manifest = Manifest::default();
+            // the name each distinct input was stored under, by hash
+            let mut stored: HashMap<String, String> = HashMap::default();
+            // the participant each anonymous name was given to
+            let mut anonymous: HashMap<String, &str> = HashMap::default();
+            // the participants whose input was copied or aliased this time
+            let mut copied: HashSet<&str> = HashSet::default();
+
             for (_, project) in config
                 .participants()
                 .iter()
                 .filter(|(_, p)| !p.skip_inputs())
             {
-                match project.input_path(year, day) {
+                // an input named after the participant must not be left behind
+                // for anyone to see, even if there is no input this time
+                let named = day_directory.join(format!("input-{}", project.username()));
+                if self.anonymize_salt.is_some() && named.is_file() {
+                    std::fs::remove_file(&named).context("Failed to remove named input")?;
+                }
+
+                let input = match project.input_fetch() {
+                    Some(source) => fetcher.fetch(source, year, day),
+                    None => project.input_path(year, day),
+                };
+
+                match input {
                     Ok(Some(path)) => {
-                        let output_name = format!("input-{}", project.username());
-                        let dest = day_directory.join(output_name);
-                        if self.copy_input(&path, &dest)? {
+                        let output_name = self.output_name(day, project.username(), &mut anonymous);
+
+                        let dest = day_directory.join(&output_name);
+                        let Some(contents) = self.read_input(project.username(), &path)? else {
+                            continue;
+                        };
+
+                        let mut record = InputRecord::new(
+                            InputKind::Official,
+                            Some(project.username()),
+                            Some(&path),
+                            &contents,
+                        );
+                        // an unchanged input keeps the record of when it was
+                        // first copied
+                        if let Some(prev) = previous.inputs.get(&output_name)
+                            && prev.sha256 == record.sha256
+                        {
+                            record = prev.clone();
+                        }
+                        if let Some(original) = stored.get(&record.sha256) {
+                            // a copy from a previous run would otherwise still
+                            // be solved and benchmarked
+                            if dest.is_file() {
+                                std::fs::remove_file(&dest)
+                                    .context("Failed to remove duplicate input")?;
+                            }
                             println!(
                                 "{}",
-                                success!(format!("  Copied input from {}", project.username()))
-                            )
+                                highlight!(format!(
+                                    "  Input from {} is identical to {}. Recording it as an alias.",
+                                    project.username(),
+                                    original
+                                ))
+                            );
+                            copied.insert(project.username());
+                            manifest.aliases.insert(
+                                output_name,
+                                Alias {
+                                    of: original.clone(),
+                                    participant: project.username().to_string(),
+                                    source: path.clone(),
+                                },
+                            );
+                            continue;
                         }
+
+                        // rewriting an unchanged input would produce a new
+                        // ciphertext every time
+                        if std::fs::read(&dest).is_ok_and(|stored| {
+                            encryption::is_stored(&stored, &contents, key.as_ref())
+                        }) {
+                            println!(
+                                "  Input from {} is unchanged as {}",
+                                project.username(),
+                                output_name
+                            );
+                        } else {
+                            let stored_contents = match key {
+                                Some(ref key) => key.encrypt(&contents)?,
+                                None => contents,
+                            };
+                            std::fs::write(&dest, stored_contents)
+                                .context("Failed to write input")?;
+                            println!(
+                                "{}",
+                                success!(format!(
+                                    "  Copied input from {} as {}",
+                                    project.username(),
+                                    output_name
+                                ))
+                            );
+                        }
+                        copied.insert(project.username());
+                        stored.insert(record.sha256.clone(), output_name.clone());
+                        manifest.inputs.insert(output_name, record);
                     }
                     Ok(None) => {
                         println!(
@@ -59,25 +240,92 @@ impl CopyInputs {
                             highlight!(format!("  No input for {}", project.username()))
                         );
                     }
-                    // if we fail to run the input command, we don't want to
-                    // error out completely.
-                    Err(_) => {
+                    // if we fail to run the input command or fetch the input,
+                    // or get a path we won't copy, we don't want to error out
+                    // completely.
+                    Err(e) => {
                         println!(
                             "{}",
                             highlight!(format!(
-                                "  Input command did not succeed for {}",
-                                project.username()
+                                "  Could not get input for {}: {:#}",
+                                project.username(),
+                                e
                             ))
                         );
                     }
                 }
             }
+
+            keep_previous_inputs(&day_directory, &previous, &copied, &mut manifest);
+            self.record_challenge_inputs(&day_directory, &previous, &mut manifest)?;
+            manifest.save(&day_directory)?;
         }
 
         Ok(())
     }
 
-    fn copy_input(&self, from: &Path, to: &Path) -> Result<bool> {
+    /// The name to store a participant's input under, which is anonymous if
+    /// there is a salt.
+    ///
+    /// `anonymous` holds the anonymous names already given out for the day.
+    fn output_name<'a>(
+        &self,
+        day: usize,
+        username: &'a str,
+        anonymous: &mut HashMap<String, &'a str>,
+    ) -> String {
+        let Some(ref salt) = self.anonymize_salt else {
+            return format!("input-{}", username);
+        };
+
+        // lengthen the name in the unlikely event that two participants collide
+        let mut len = 4;
+        let name = loop {
+            let name = inputs::anonymous_name(salt, day, username, len);
+            if anonymous.get(&name).is_none_or(|u| *u == username) {
+                break name;
+            }
+            len += 1;
+        };
+        anonymous.insert(name.clone(), username);
+
+        name
+    }
+
+    /// Add the challenge inputs in the day directory to the manifest.
+    ///
+    /// Challenge inputs are added by hand, so their records are kept from the
+    /// `previous` manifest unless their contents changed.
+    fn record_challenge_inputs(
+        &self,
+        day_directory: &Path,
+        previous: &Manifest,
+        manifest: &mut Manifest,
+    ) -> Result<()> {
+        for entry in std::fs::read_dir(day_directory)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if InputKind::from_name(&name) != Some(InputKind::Challenge) {
+                continue;
+            }
+
+            let contents = read_plain(&entry.path())?;
+            let record = match previous.inputs.get(&name) {
+                Some(record) if record.sha256 == inputs::sha256(&contents) => record.clone(),
+                _ => {
+                    println!("  Recorded challenge input {}", name);
+                    InputRecord::new(InputKind::Challenge, None, None, &contents)
+                }
+            };
+            manifest.inputs.insert(name, record);
+        }
+
+        Ok(())
+    }
+
+    /// Read and check the input, returning the contents to store, or `None`
+    /// if it should be skipped.
+    fn read_input(&self, username: &str, from: &Path) -> Result<Option<Vec<u8>>> {
         if !from.is_file() {
             println!(
                 "{}",
@@ -86,19 +334,108 @@ impl CopyInputs {
                     from.to_string_lossy()
                 ))
             );
-            return Ok(false);
+            return Ok(None);
         }
 
-        if std::fs::read_to_string(from)?.lines().count() < 1 {
+        let size = match from.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(e) => {
+                println!(
+                    "{}",
+                    failure!(format!(
+                        "  Could not read the size of the input from {}: {}",
+                        username, e
+                    ))
+                );
+                return Ok(None);
+            }
+        };
+        if size > self.max_input_size {
+            println!(
+                "{}",
+                failure!(format!(
+                    "  Input from {} is {} bytes, which is larger than the maximum of {} bytes",
+                    username, size, self.max_input_size
+                ))
+            );
+            return Ok(None);
+        }
+
+        let raw = match std::fs::read(from) {
+            Ok(raw) => raw,
+            Err(e) => {
+                println!(
+                    "{}",
+                    failure!(format!("  Could not read input from {}: {}", username, e))
+                );
+                return Ok(None);
+            }
+        };
+        let contents = match inputs::decode(&raw) {
+            Ok(contents) => contents,
+            Err(e) => {
+                println!(
+                    "{}",
+                    failure!(format!("  Rejected input from {}: {:#}", username, e))
+                );
+                return Ok(None);
+            }
+        };
+
+        if contents.lines().count() < 1 {
             println!(
                 "  {}",
                 highlight!(format!("Skipping empty file {}", from.display()))
             );
-            return Ok(false);
+            return Ok(None);
+        }
+
+        if self.normalize {
+            let (normalized, changes) = inputs::normalize(contents);
+            for change in changes.iter() {
+                println!(
+                    "{}",
+                    highlight!(format!("  Input from {}: {}", username, change))
+                );
+            }
+            Ok(Some(normalized.into_bytes()))
+        } else {
+            Ok(Some(raw))
         }
+    }
+}
+
+/// Keep the `previous` records of the inputs of participants whose input was
+/// not copied this time, as long as the input is still stored.
+///
+/// An input that could not be copied again is still solved and benchmarked,
+/// so it must stay in the manifest.
+fn keep_previous_inputs(
+    day_directory: &Path,
+    previous: &Manifest,
+    copied: &HashSet<&str>,
+    manifest: &mut Manifest,
+) {
+    let not_copied = |participant: &str| !copied.contains(participant);
 
-        std::fs::copy(from, to)?;
+    for (name, record) in previous.inputs.iter() {
+        if record.kind == InputKind::Official
+            && record.participant.as_deref().is_some_and(not_copied)
+            && !manifest.inputs.contains_key(name)
+            && day_directory.join(name).is_file()
+        {
+            println!("  Keeping the previous input {}", name);
+            manifest.inputs.insert(name.clone(), record.clone());
+        }
+    }
 
-        Ok(true)
+    for (name, alias) in previous.aliases.iter() {
+        if not_copied(&alias.participant)
+            && !manifest.aliases.contains_key(name)
+            && manifest.inputs.contains_key(&alias.of)
+        {
+            println!("  Keeping the previous alias {} of {}", name, alias.of);
+            manifest.aliases.insert(name.clone(), alias.clone());
+        }
     }
 }
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use clap::Args;
+
+use crate::{
+    config::Config,
+    encryption::{Key, is_encrypted},
+    inputs::InputKind,
+    success,
+};
+
+/// Decrypt the inputs stored by `copy-inputs --encrypt`, for debugging.
+///
+/// Every input in the inputs directory is written in plaintext to the same
+/// place in the destination, using the key from `AOC_TOOLS_INPUT_KEY`. Inputs
+/// that are not encrypted are copied as they are. Nothing else is copied.
+#[derive(Debug, Clone, Args)]
+pub struct DecryptInputs {
+    /// The root directory where inputs are stored.
+    inputs: PathBuf,
+
+    /// The directory to write the decrypted inputs to.
+    ///
+    /// This must not be the inputs directory, so that decrypted inputs are
+    /// never committed by accident.
+    destination: PathBuf,
+}
+
+impl DecryptInputs {
+    pub fn run(&self, _config: &Config) -> Result<()> {
+        if !self.inputs.is_dir() {
+            bail!("Inputs must exist and be a directory");
+        }
+
+        let key = Key::from_env()?;
+
+        std::fs::create_dir_all(&self.destination)
+            .context("Failed to create destination directory")?;
+
+        if self.inputs.canonicalize()? == self.destination.canonicalize()? {
+            bail!("The destination must not be the inputs directory");
+        }
+
+        let mut days: Vec<_> = std::fs::read_dir(&self.inputs)?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir() && e.file_name().to_string_lossy().starts_with("day_"))
+            .collect();
+        days.sort_by_key(|e| e.file_name());
+
+        for day in days {
+            let destination = self.destination.join(day.file_name());
+            std::fs::create_dir_all(&destination).context("Failed to make directory")?;
+
+            let mut decrypted = 0;
+            for entry in std::fs::read_dir(day.path())? {
+                let entry = entry?;
+                if InputKind::from_name(&entry.file_name().to_string_lossy()).is_none() {
+                    continue;
+                }
+
+                let mut contents = std::fs::read(entry.path())?;
+                if is_encrypted(&contents) {
+                    contents = key.decrypt(&contents).with_context(|| {
+                        format!("Failed to decrypt '{}'", entry.path().display())
+                    })?;
+                    decrypted += 1;
+                }
+                std::fs::write(destination.join(entry.file_name()), contents)
+                    .context("Failed to write input")?;
+            }
+
+            println!(
+                "{}",
+                success!(format!(
+                    "> {}: decrypted {} input(s)",
+                    day.file_name().to_string_lossy(),
+                    decrypted
+                ))
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -8,9 +8,11 @@ use crate::config::Config;
 mod bench;
 mod check_solutions;
 mod copy_inputs;
+mod decrypt_inputs;
 mod report;
 mod solve_inputs;
 mod summary;
+mod sync_participants;
 
 /// CI-related commands
 #[derive(Debug, Clone, Args)]
@@ -39,9 +41,11 @@ pub enum Commands {
     Bench(bench::Bench),
     CheckSolutions(check_solutions::CheckSolutions),
     CopyInputs(copy_inputs::CopyInputs),
+    DecryptInputs(decrypt_inputs::DecryptInputs),
     Report(report::Report),
     SolveInputs(solve_inputs::SolveInputs),
     Summary(summary::Summary),
+    SyncParticipants(sync_participants::SyncParticipants),
 }
 
 impl Commands {
@@ -50,9 +54,11 @@ impl Commands {
             Self::Bench(cmd) => cmd.run(config),
             Self::CheckSolutions(cmd) => cmd.run(config),
             Self::CopyInputs(cmd) => cmd.run(config),
+            Self::DecryptInputs(cmd) => cmd.run(config),
             Self::Report(cmd) => cmd.run(config),
             Self::SolveInputs(cmd) => cmd.run(config),
             Self::Summary(cmd) => cmd.run(config),
+            Self::SyncParticipants(cmd) => cmd.run(config),
         }
     }
 }
@@ -1,10 +1,12 @@
-use std::{collections::HashSet, fs::File, io::Write, path::PathBuf};
+use std::{collections::BTreeMap, fs::File, io::Write, path::PathBuf};
 
 use anyhow::{Context, Result};
 use clap::Args;
 use minijinja::{Environment, context};
 
-use crate::{config::Config, highlight, success, util::day_directory_name};
+use crate::{
+    bench_data::Participants, config::Config, highlight, success, util::day_directory_name,
+};
 
 /// Generates a benchmark report for a given day.
 #[derive(Debug, Clone, Args)]
@@ -60,22 +62,42 @@ impl Report {
 
         // we need to determine the list of participants that actually solve
         // this day's problem
-        let participants_raw = std::fs::read_to_string(day_directory.join("participants.json"))
-            .context("Could not open participants file")?;
-        let participant_names: HashSet<String> = serde_json::from_str(&participants_raw)
-            .context("Failed to deserialize participants")?;
+        let participant_record =
+            Participants::load(&day_directory).context("Could not open participants file")?;
 
         let participants: Vec<_> = config
             .participants()
             .iter()
-            .filter(|(n, _)| participant_names.contains(*n))
+            .filter(|(n, _)| participant_record.contains_key(*n))
             .map(|(_, p)| p)
             .collect();
 
+        // the revision of each participant's benchmarked code, by username
+        let revisions: BTreeMap<_, _> = config
+            .participants()
+            .iter()
+            .filter_map(|(n, p)| {
+                participant_record
+                    .get(n)
+                    .cloned()
+                    .flatten()
+                    .map(|r| (p.username(), r))
+            })
+            .collect();
+
         // fetch the benchmarks table
         let official_benchmarks = std::fs::read_to_string(day_directory.join("benches.md"))
             .context("Could not open benchmark file")?;
 
+        // fetch the breakdown by part, which only exists if someone's parts
+        // were timed individually
+        let parts_file = day_directory.join("benches_parts.md");
+        let part_benchmarks = if parts_file.is_file() {
+            Some(std::fs::read_to_string(parts_file).context("Could not open parts file")?)
+        } else {
+            None
+        };
+
         // fetch the solutions table
         let solutions = std::fs::read_to_string(day_directory.join("solutions.md"))
             .context("Could not open solution file")?;
@@ -86,7 +108,9 @@ impl Report {
                 day => self.day,
                 pipeline_url => config.pipeline_url(),
                 participants,
+                revisions,
                 official_benchmarks,
+                part_benchmarks,
                 solutions
             })
             .context("Failed to render template")?;
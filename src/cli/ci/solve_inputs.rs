@@ -11,6 +11,7 @@ use walkdir::WalkDir;
 use crate::{
     config::Config,
     highlight,
+    inputs::{Manifest, PlainInputs, Problem},
     solution::Solutions,
     success,
     util::{day_directory_name, sanitize_value_for_display},
@@ -22,6 +23,16 @@ use crate::{
 /// assumes the specified inputs path to have paths like day_001, day_002, etc.
 ///
 /// For any given solution, this will time out after the configured timeout.
+///
+/// Inputs recorded as aliases of another input in the day's manifest are
+/// skipped, as are inputs that were modified after they were copied. Any
+/// difference between the inputs and the manifest is reported.
+///
+/// Encrypted inputs are decrypted to a temporary directory with the key from
+/// `AOC_TOOLS_INPUT_KEY`.
+///
+/// If the solver's location is a git repo, each solution records the commit
+/// of the solver, and whether it had uncommitted changes.
 #[derive(Debug, Clone, Args)]
 pub struct SolveInputs {
     /// The root directory where inputs are stored.
@@ -37,19 +48,27 @@ impl SolveInputs {
             bail!("Inputs must exist and be a directory");
         }
 
-        let (_, solver) = config
+        let (solver_name, solver) = config
             .participants()
             .iter()
             .find(|(_, p)| p.is_solver())
             .ok_or_else(|| anyhow!("Config does not specify at one participant as the solver"))?;
 
+        // every solution records the version of the solver that produced it
+        let revision = solver.revision()?;
+        if let Some(ref revision) = revision {
+            println!("> Solving with {} at {}", solver_name, revision);
+        }
+
         let year = config.year();
+        let plain = PlainInputs::open(&self.inputs, 1..=config.days())?;
 
         'days: for day in 1..=config.days() {
             println!();
 
             let day_directory_name = day_directory_name(day);
             let day_directory = self.inputs.join(&day_directory_name);
+            let input_directory = plain.path().join(&day_directory_name);
 
             if !day_directory.is_dir() {
                 println!("> No inputs for day {}", day);
@@ -62,7 +81,16 @@ impl SolveInputs {
             // being sensitive to changing key ordering with a HashMap
             let mut solutions = Solutions::default();
 
-            for entry in WalkDir::new(&day_directory)
+            let manifest = Manifest::load(&day_directory)?;
+            for (name, alias) in manifest.aliases.iter() {
+                println!("  {} is identical to {}", name, alias.of);
+            }
+            let problems = manifest.verify(&input_directory)?;
+            for problem in problems.iter() {
+                println!("  {}", highlight!(problem.to_string()));
+            }
+
+            for entry in WalkDir::new(&input_directory)
                 .into_iter()
                 .filter_map(|e| e.ok())
             {
@@ -73,13 +101,24 @@ impl SolveInputs {
                     continue;
                 }
 
+                // duplicates are only solved once, under their original name
+                if manifest.is_alias(&filename) {
+                    continue;
+                }
+
+                // an input changed since it was copied is not the participant's
+                if problems.contains(&Problem::Modified(filename.to_string())) {
+                    continue;
+                }
+
                 let input = entry.path().canonicalize()?;
-                if let Some(solution) = solver
+                if let Some(mut solution) = solver
                     .solve(year, day, &input, Some(config.timeout()))
                     .with_context(|| {
                         format!("Failed to solve day {} for input {}", day, filename)
                     })?
                 {
+                    solution.set_revision(revision.clone());
                     solutions.insert(filename.to_string(), solution);
                 } else {
                     println!(
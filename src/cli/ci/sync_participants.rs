@@ -0,0 +1,75 @@
+use anyhow::{Result, bail};
+use clap::Args;
+
+use crate::{config::Config, failure, success};
+
+/// Clone or update every participant's repo.
+///
+/// Each repo is cloned into the participant's `location` if it does not exist
+/// yet, or fetched otherwise, after which the participant's `ref` (or the
+/// default branch, if unset) is checked out. A location with uncommitted
+/// changes is left untouched.
+///
+/// Every participant is synced even if some of them fail, and the command
+/// fails afterwards if any did.
+#[derive(Debug, Clone, Args)]
+pub struct SyncParticipants {
+    /// Only sync the specified participant(s).
+    ///
+    /// May be specified multiple times.
+    #[arg(short, long = "participant")]
+    participants: Vec<String>,
+}
+
+impl SyncParticipants {
+    pub fn run(&self, config: &Config) -> Result<()> {
+        for name in self.participants.iter() {
+            if !config.participants().contains_key(name) {
+                bail!("Participant does not exist: {}", name);
+            }
+        }
+
+        println!("> Syncing participants");
+
+        let mut failed = Vec::default();
+
+        for (name, project) in config
+            .participants()
+            .iter()
+            .filter(|(n, _)| self.participants.is_empty() || self.participants.contains(n))
+        {
+            let target = project.git_ref().unwrap_or("default branch");
+
+            match project.sync() {
+                Ok(synced) => {
+                    let short = |c: &str| c.get(..12).unwrap_or(c).to_string();
+                    let status = if synced.cloned {
+                        format!("cloned at {}", short(&synced.current))
+                    } else if synced.previous.as_deref() == Some(synced.current.as_str()) {
+                        format!("up to date at {}", short(&synced.current))
+                    } else {
+                        format!(
+                            "updated {} -> {}",
+                            synced.previous.as_deref().map(short).unwrap_or_default(),
+                            short(&synced.current)
+                        )
+                    };
+                    println!(
+                        "  {}",
+                        success!(format!("{} ({}): {}", name, target, status))
+                    );
+                }
+                Err(e) => {
+                    println!("  {}", failure!(format!("{} ({}): {:#}", name, target, e)));
+                    failed.push(name.as_str());
+                }
+            }
+        }
+
+        if !failed.is_empty() {
+            bail!("Failed to sync: {}", failed.join(", "));
+        }
+
+        Ok(())
+    }
+}
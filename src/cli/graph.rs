@@ -1,7 +1,7 @@
 use std::{collections::BTreeMap, path::PathBuf};
 
 use anyhow::{Result, bail};
-use clap::Args;
+use clap::{Args, ValueEnum};
 #[cfg(feature = "image_export")]
 use plotly::ImageFormat;
 use plotly::{
@@ -18,6 +18,10 @@ pub struct Graph {
     /// The path to a combined benches CSV
     input: PathBuf,
 
+    /// The metric to graph.
+    #[clap(long, value_enum, default_value_t = Metric::default())]
+    metric: Metric,
+
     /// If set, stores a HTML representation of the graphs.
     #[clap(long)]
     output_html: Option<PathBuf>,
@@ -44,7 +48,11 @@ impl Graph {
             out
         };
 
-        let accumulated = accumulated_graph(&benches);
+        let accumulated = if self.metric == Metric::Parts {
+            parts_graph(&benches)
+        } else {
+            accumulated_graph(&benches, self.metric)
+        };
 
         if let Some(ref output_html) = self.output_html {
             println!("> saving html");
@@ -66,25 +74,96 @@ impl Graph {
     }
 }
 
-fn accumulated_graph(benches: &[BenchCSVRow]) -> Plot {
+/// The metric to graph.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Metric {
+    /// The mean runtime.
+    #[default]
+    Runtime,
+    /// The largest peak resident set size.
+    ///
+    /// Benches without memory data are ignored.
+    Memory,
+    /// The mean runtime of each part, for participants that time their parts
+    /// individually.
+    ///
+    /// Unified CSVs only contain these benches if written with
+    /// `unify-benches --parts-output`.
+    Parts,
+    /// The input size in bytes divided by the mean runtime.
+    ///
+    /// Benches without throughput data are ignored.
+    Throughput,
+    /// The number of input lines divided by the mean runtime.
+    ///
+    /// Benches without throughput data are ignored.
+    LineThroughput,
+}
+
+impl Metric {
+    /// Extract the value for this metric from a bench, in display units.
+    fn value(&self, bench: &BenchCSVRow) -> Option<f64> {
+        // the other metrics are for running both parts
+        if bench.part.is_some() != (*self == Self::Parts) {
+            return None;
+        }
+
+        match self {
+            // we are going to convert from seconds to ms
+            Self::Runtime | Self::Parts => Some(bench.mean * 1000.0),
+            // and from bytes to MiB
+            Self::Memory => bench
+                .peak_rss_max
+                .map(|bytes| bytes as f64 / (1024.0 * 1024.0)),
+            Self::Throughput => bench.bytes_per_sec.map(|b| b / (1024.0 * 1024.0)),
+            Self::LineThroughput => bench.lines_per_sec,
+        }
+    }
+
+    fn layout(&self) -> Layout {
+        match self {
+            Self::Runtime => Layout::new()
+                .bar_mode(BarMode::Stack)
+                .title("Total runtime by day (lower is better)")
+                .y_axis(Axis::new().title("Time (ms)")),
+            Self::Memory => Layout::new()
+                .bar_mode(BarMode::Group)
+                .title("Peak memory usage by day (lower is better)")
+                .y_axis(Axis::new().title("Peak RSS (MiB)")),
+            Self::Throughput => Layout::new()
+                .bar_mode(BarMode::Group)
+                .title("Throughput by day (higher is better)")
+                .y_axis(Axis::new().title("Throughput (MiB/s)")),
+            Self::LineThroughput => Layout::new()
+                .bar_mode(BarMode::Group)
+                .title("Throughput by day (higher is better)")
+                .y_axis(Axis::new().title("Throughput (lines/s)")),
+            Self::Parts => Layout::new()
+                .bar_mode(BarMode::Stack)
+                .title("Total runtime by part (lower is better)")
+                .y_axis(Axis::new().title("Time (ms)")),
+        }
+    }
+}
+
+fn accumulated_graph(benches: &[BenchCSVRow], metric: Metric) -> Plot {
     let mut plot = Plot::new();
 
-    let layout = Layout::new()
-        .bar_mode(BarMode::Stack)
-        .title("Total runtime by day (lower is better)")
-        .height(1000)
-        .colorway(default_colorway())
-        .y_axis(Axis::new().title("Time (ms)"));
+    let layout = metric.layout().height(1000).colorway(default_colorway());
     plot.set_layout(layout);
 
     // aggregate into a more useful datastructure
     let mut map: BTreeMap<String, [Vec<f64>; 25]> = BTreeMap::default();
     for bench in benches.iter() {
+        let Some(value) = metric.value(bench) else {
+            continue;
+        };
+
         let e = map
             .entry(format!("{} ({})", &bench.participant, &bench.language))
             .or_default();
         let day_idx = bench.day - 1;
-        e[day_idx].push(bench.mean);
+        e[day_idx].push(value);
     }
 
     // btree already sorted
@@ -98,15 +177,7 @@ fn accumulated_graph(benches: &[BenchCSVRow]) -> Plot {
             .map(|p| {
                 // unwrap should be safe because we made participants from the
                 // keys.
-                let vals = &map.get(p.as_str()).unwrap()[day_index];
-
-                // we are going to convert from seconds to ms
-                1000.0
-                    * match vals.len() {
-                        0 => 0.0,
-                        1 => vals[1],
-                        x => vals.iter().sum::<f64>() / x as f64,
-                    }
+                day_value(&map.get(p.as_str()).unwrap()[day_index])
             })
             .collect();
 
@@ -122,6 +193,63 @@ fn accumulated_graph(benches: &[BenchCSVRow]) -> Plot {
     plot
 }
 
+/// The value to graph for a participant's day, which is the mean of their
+/// values for that day, or zero if they have none.
+fn day_value(vals: &[f64]) -> f64 {
+    match vals.len() {
+        0 => 0.0,
+        1 => vals[0],
+        x => vals.iter().sum::<f64>() / x as f64,
+    }
+}
+
+/// Graph the total time each participant spends in each part, summed over
+/// the days for which their parts were timed.
+fn parts_graph(benches: &[BenchCSVRow]) -> Plot {
+    let mut plot = Plot::new();
+
+    let layout = Metric::Parts
+        .layout()
+        .height(1000)
+        .colorway(default_colorway());
+    plot.set_layout(layout);
+
+    // the mean over the inputs for every participant, day, and part
+    let mut map: BTreeMap<String, BTreeMap<(usize, u8), Vec<f64>>> = BTreeMap::default();
+    for bench in benches.iter() {
+        let (Some(value), Some(part)) = (Metric::Parts.value(bench), bench.part) else {
+            continue;
+        };
+
+        map.entry(format!("{} ({})", &bench.participant, &bench.language))
+            .or_default()
+            .entry((bench.day, part))
+            .or_default()
+            .push(value);
+    }
+
+    let participants: Vec<_> = map.keys().cloned().collect();
+
+    for part in [1, 2] {
+        let data: Vec<f64> = participants
+            .iter()
+            .map(|p| {
+                map.get(p.as_str())
+                    .unwrap()
+                    .iter()
+                    .filter(|((_, pt), _)| *pt == part)
+                    .map(|(_, vals)| vals.iter().sum::<f64>() / vals.len() as f64)
+                    .sum()
+            })
+            .collect();
+
+        let trace = Bar::new(participants.clone(), data).name(format!("part {}", part));
+        plot.add_trace(trace);
+    }
+
+    plot
+}
+
 fn default_colorway() -> Vec<Rgb> {
     vec![
         Rgb::new(114, 229, 239),
@@ -151,3 +279,29 @@ fn default_colorway() -> Vec<Rgb> {
         Rgb::new(155, 27, 92),
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn day_value() {
+        assert_eq!(super::day_value(&[]), 0.0);
+        // a single value used to be read out of bounds
+        assert_eq!(super::day_value(&[2.0]), 2.0);
+        assert_eq!(super::day_value(&[1.0, 2.0, 6.0]), 3.0);
+    }
+
+    #[test]
+    fn single_bench_per_day() {
+        let benches = vec![BenchCSVRow {
+            day: 1,
+            participant: "alice".into(),
+            language: "rust".into(),
+            mean: 0.002,
+            ..Default::default()
+        }];
+
+        accumulated_graph(&benches, Metric::Runtime);
+    }
+}
@@ -1,10 +1,15 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
 use anyhow::{Context, Result, bail};
 use clap::Args;
 use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
 
-use crate::bench_data::BenchCSVRow;
+use crate::{
+    bench_data::{BenchCSVRow, load_full_benches},
+    host::BenchEnv,
+    util::day_directory_name,
+};
 
 use super::ApiCommon;
 
@@ -18,29 +23,71 @@ pub struct PublishBenches {
     /// The path to a unified benches CSV.
     benches: PathBuf,
 
+    /// The root directory where bench data is stored.
+    ///
+    /// If specified, the bench_env.json file in each `<day>_<padded number>`
+    /// directory is attached to the published benches for that day, as `env`.
+    #[clap(short, long)]
+    inputs: Option<PathBuf>,
+
     #[clap(flatten)]
     api: ApiCommon,
 }
 
+/// A bench as published, with the environment it was taken in.
+#[derive(Debug, Serialize)]
+struct PublishedBench<'a> {
+    #[serde(flatten)]
+    row: &'a BenchCSVRow,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    env: Option<&'a BenchEnv>,
+}
+
 impl PublishBenches {
     pub fn run(&self) -> Result<()> {
         if !self.benches.is_file() {
             bail!("Benches path does not exist or is not a file");
         }
 
-        let benches: Vec<BenchCSVRow> = {
-            let mut out = Vec::default();
-            let mut reader = csv::Reader::from_path(&self.benches)?;
-            for result in reader.deserialize() {
-                out.push(result?);
-            }
-            out
-        };
+        // only full runs are published, as the service has no notion of parts
+        let mut benches: Vec<BenchCSVRow> = Vec::default();
+        let skipped = load_full_benches(&self.benches, &mut benches)?;
+        if skipped > 0 {
+            println!("> Skipping {} benches of individual parts", skipped);
+        }
 
         if benches.is_empty() {
             bail!("Specified benches CSV is empty");
         }
 
+        // the year of every bench _should_ be the same in a unified CSV
+        let year = benches[0].year;
+
+        // the environments are read up front so that a bad file can't stop us
+        // after the benches were already published
+        let mut envs: HashMap<usize, BenchEnv> = HashMap::default();
+        if let Some(ref inputs) = self.inputs {
+            for day in 1..=25 {
+                let env_file = inputs.join(day_directory_name(day)).join("bench_env.json");
+                if env_file.is_file() {
+                    let env = BenchEnv::from_file(&env_file)?;
+                    if env.year == year {
+                        envs.insert(day, env);
+                    }
+                }
+            }
+
+            println!("> Attaching {} bench environments", envs.len());
+        }
+
+        let payload: Vec<_> = benches
+            .iter()
+            .map(|row| PublishedBench {
+                row,
+                env: envs.get(&row.day).filter(|_| row.year == year),
+            })
+            .collect();
+
         let client = reqwest::blocking::Client::new();
 
         let publish_endpoint = self.api.api_base.join("v1/benchmarks")?;
@@ -57,7 +104,7 @@ impl PublishBenches {
             .post(publish_endpoint)
             .bearer_auth(&self.api.api_token)
             .header(CONTENT_TYPE, "application/json")
-            .json(&benches)
+            .json(&payload)
             .send()
             .context("Failed to publish benches")?;
 
@@ -65,11 +112,9 @@ impl PublishBenches {
             bail!("Publish was unsuccessful.\n{:#?}", res);
         }
 
-        println!("> Regenerating summaries via {}", &generate_endpoint);
         // now we want to regenerate the summaries corresponding to the current
-        // year, which we can determine by getting the first benchmark since all
-        // the years _should_ be the same from a unified CSV.
-        let year = benches[0].year;
+        // year
+        println!("> Regenerating summaries via {}", &generate_endpoint);
 
         let res = client
             .post(generate_endpoint)
@@ -11,6 +11,10 @@ use crate::{
 };
 
 /// Combine the `benches_raw.csv` files for every day into a single CSV file.
+///
+/// The benches of individual parts are left out, as anything that doesn't know
+/// about parts would count them alongside the full runs. They can be written to
+/// a separate file with `--parts-output`, for graphing with `--metric parts`.
 #[derive(Debug, Clone, Args)]
 pub struct UnifyBenches {
     /// The root directory where bench data is stored.
@@ -22,6 +26,10 @@ pub struct UnifyBenches {
     /// The path to store the unified output file.
     #[clap(short, long)]
     output: PathBuf,
+
+    /// The path to store the benches of individual parts.
+    #[clap(long)]
+    parts_output: Option<PathBuf>,
 }
 
 impl UnifyBenches {
@@ -63,12 +71,27 @@ impl UnifyBenches {
             highlight!(self.output.display())
         );
 
+        let (full, parts): (Vec<_>, Vec<_>) = unified.into_iter().partition(|r| r.part.is_none());
+
         let mut writer = csv::Writer::from_path(&self.output)?;
-        for record in unified {
+        for record in full {
             writer.serialize(record)?;
         }
         writer.flush()?;
 
+        if let Some(ref parts_output) = self.parts_output {
+            println!(
+                "> Writing the benches of individual parts to {}",
+                highlight!(parts_output.display())
+            );
+
+            let mut writer = csv::Writer::from_path(parts_output)?;
+            for record in parts {
+                writer.serialize(record)?;
+            }
+            writer.flush()?;
+        }
+
         Ok(())
     }
 }
@@ -1,6 +1,6 @@
 use std::{collections::BTreeMap, path::Path};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use figment::{
     Figment,
     providers::{Format, Toml},
@@ -8,7 +8,7 @@ use figment::{
 use serde::Deserialize;
 use url::Url;
 
-use crate::aoc_project::AocProject;
+use crate::{aoc_project::AocProject, input_selection::InputSelection, limits::Limits};
 
 fn default_timeout() -> usize {
     30
@@ -38,14 +38,52 @@ pub struct General {
     timeout: usize,
     #[serde(default = "default_max_inputs")]
     max_inputs_per_bench: usize,
+    #[serde(default)]
+    input_selection: InputSelection,
+    #[serde(default)]
+    bench_cpus: Vec<usize>,
+    #[serde(default)]
+    limits: Limits,
 }
 
 impl Config {
     pub fn load(path: &Path) -> Result<Self> {
-        Figment::new()
+        let mut config: Self = Figment::new()
             .merge(Toml::file(path))
             .extract()
-            .context("Invalid config file")
+            .context("Invalid config file")?;
+
+        // no participant's code may read anyone's session token
+        let session_vars: Vec<String> = config
+            .participants
+            .values()
+            .filter_map(|p| p.input_fetch())
+            .map(|source| source.session_env.clone())
+            .collect();
+
+        // participants only need to specify the limits that differ
+        for project in config.participants.values_mut() {
+            project.inherit_limits(config.general.limits);
+            project.inherit_session_vars(session_vars.clone());
+        }
+
+        for (name, project) in config.participants.iter() {
+            if !project.skip_inputs() && !project.has_input_source() {
+                bail!(
+                    "Participant '{}' needs an input_cmd or input_fetch, or skip_inputs",
+                    name
+                );
+            }
+
+            // a ref that looks like an option could be passed to git as one
+            if let Some(git_ref) = project.git_ref()
+                && git_ref.starts_with('-')
+            {
+                bail!("Participant '{}' has an invalid ref '{}'", name, git_ref);
+            }
+        }
+
+        Ok(config)
     }
 
     pub fn participants(&self) -> &BTreeMap<String, AocProject> {
@@ -71,4 +109,13 @@ impl Config {
     pub fn max_inputs(&self) -> usize {
         self.general.max_inputs_per_bench
     }
+
+    pub fn input_selection(&self) -> &InputSelection {
+        &self.general.input_selection
+    }
+
+    /// The CPUs to pin benchmarks to. Empty if benchmarks should not be pinned.
+    pub fn bench_cpus(&self) -> &[usize] {
+        &self.general.bench_cpus
+    }
 }
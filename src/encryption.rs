@@ -0,0 +1,148 @@
+//! Encryption of inputs at rest.
+//!
+//! An encrypted file starts with a marker, followed by a random nonce and the
+//! contents encrypted with XChaCha20-Poly1305. The key is the SHA-256 of the
+//! secret in the `AOC_TOOLS_INPUT_KEY` environment variable, which should be a
+//! long random string such as the output of `openssl rand -hex 32`.
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use chacha20poly1305::{
+    KeyInit, XChaCha20Poly1305, XNonce,
+    aead::{Aead, AeadCore},
+};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+
+/// The environment variable holding the secret the key is derived from.
+pub const KEY_VAR: &str = "AOC_TOOLS_INPUT_KEY";
+
+/// The start of every encrypted file.
+const MARKER: &[u8] = b"aoc-tools-encrypted-v1\n";
+
+const NONCE_LEN: usize = 24;
+
+/// The key used to encrypt and decrypt inputs.
+pub struct Key(XChaCha20Poly1305);
+
+impl Key {
+    /// Derive a key from the given secret.
+    pub fn new(secret: &str) -> Result<Self> {
+        if secret.is_empty() {
+            bail!("The secret for encrypting inputs must not be empty");
+        }
+
+        let key = Sha256::digest(secret.as_bytes());
+        Ok(Self(XChaCha20Poly1305::new(&key)))
+    }
+
+    /// Derive the key from the secret in [KEY_VAR].
+    pub fn from_env() -> Result<Self> {
+        let secret = std::env::var(KEY_VAR).map_err(|_| anyhow!("{} is not set", KEY_VAR))?;
+        Self::new(&secret)
+    }
+
+    pub fn encrypt(&self, contents: &[u8]) -> Result<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .0
+            .encrypt(&nonce, contents)
+            .map_err(|_| anyhow!("Failed to encrypt input"))?;
+
+        let mut out = Vec::with_capacity(MARKER.len() + NONCE_LEN + ciphertext.len());
+        out.extend_from_slice(MARKER);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+
+        Ok(out)
+    }
+
+    pub fn decrypt(&self, contents: &[u8]) -> Result<Vec<u8>> {
+        let Some(rest) = contents.strip_prefix(MARKER) else {
+            bail!("Contents are not encrypted");
+        };
+
+        if rest.len() < NONCE_LEN {
+            bail!("Encrypted contents are truncated");
+        }
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        self.0
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                anyhow!("Failed to decrypt input, either the key is wrong or it is corrupt")
+            })
+    }
+}
+
+/// Whether the contents were produced by [Key::encrypt].
+pub fn is_encrypted(contents: &[u8]) -> bool {
+    contents.starts_with(MARKER)
+}
+
+/// Whether the `stored` contents of a file already hold `contents`, encrypted
+/// with the key if there is one.
+///
+/// Every encryption uses a new nonce, so an unchanged input has to be detected
+/// this way to avoid rewriting it.
+pub fn is_stored(stored: &[u8], contents: &[u8], key: Option<&Key>) -> bool {
+    match key {
+        Some(key) => is_encrypted(stored) && key.decrypt(stored).is_ok_and(|c| c == contents),
+        None => stored == contents,
+    }
+}
+
+/// Read a file, decrypting it with the key from the environment if it is
+/// encrypted.
+pub fn read_plain(path: &Path) -> Result<Vec<u8>> {
+    let contents =
+        std::fs::read(path).with_context(|| format!("Failed to read '{}'", path.display()))?;
+
+    if !is_encrypted(&contents) {
+        return Ok(contents);
+    }
+
+    Key::from_env()
+        .context("Inputs are encrypted")?
+        .decrypt(&contents)
+        .with_context(|| format!("Failed to decrypt '{}'", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let key = Key::new("secret").unwrap();
+        let contents = b"1\n2\n3\n";
+
+        let encrypted = key.encrypt(contents).unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!is_encrypted(contents));
+        assert_ne!(&encrypted[MARKER.len()..], contents);
+        // every encryption uses a new nonce
+        assert_ne!(encrypted, key.encrypt(contents).unwrap());
+
+        assert_eq!(key.decrypt(&encrypted).unwrap(), contents);
+        assert!(Key::new("wrong").unwrap().decrypt(&encrypted).is_err());
+        assert!(key.decrypt(contents).is_err());
+        assert!(key.decrypt(&encrypted[..MARKER.len() + 4]).is_err());
+        assert!(Key::new("").is_err());
+    }
+
+    #[test]
+    fn is_stored() {
+        let key = Key::new("secret").unwrap();
+        let contents = b"1\n2\n3\n";
+        let encrypted = key.encrypt(contents).unwrap();
+
+        assert!(super::is_stored(&encrypted, contents, Some(&key)));
+        assert!(!super::is_stored(&encrypted, b"1\n", Some(&key)));
+        assert!(!super::is_stored(&encrypted, contents, None));
+        assert!(!super::is_stored(contents, contents, Some(&key)));
+        assert!(super::is_stored(contents, contents, None));
+        let other = Key::new("other").unwrap();
+        assert!(!super::is_stored(&encrypted, contents, Some(&other)));
+    }
+}
@@ -0,0 +1,327 @@
+//! Fetching participants' inputs over HTTP from the puzzle site, as an
+//! alternative to their `input_cmd`.
+//!
+//! Every fetched input is cached on disk, per site and session, so that each
+//! input is only ever requested once, and requests are spaced out by a
+//! minimum interval. Only inputs that pass the same checks as copied inputs
+//! are cached.
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use reqwest::{StatusCode, header};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    inputs::{self, sha256},
+    util::day_directory_name,
+};
+
+/// Where to fetch a participant's inputs from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputFetch {
+    /// The base URL of the site, such as `https://adventofcode.com`.
+    pub base_url: Url,
+    /// The environment variable holding the participant's session token.
+    pub session_env: String,
+}
+
+impl InputFetch {
+    /// The URL of the input for the given day.
+    pub fn url(&self, year: usize, day: usize) -> Result<Url> {
+        let base = self.base_url.as_str().trim_end_matches('/');
+        Url::parse(&format!("{}/{}/day/{}/input", base, year, day)).context("Invalid input URL")
+    }
+
+    fn session(&self, env: fn(&str) -> Option<String>) -> Result<String> {
+        env(&self.session_env).ok_or_else(|| anyhow!("{} is not set", self.session_env))
+    }
+}
+
+/// Fetches inputs, caching them and limiting the rate of requests.
+#[derive(Debug)]
+pub struct Fetcher {
+    client: reqwest::blocking::Client,
+    cache: PathBuf,
+    interval: Duration,
+    max_size: u64,
+    last_request: Option<Instant>,
+    /// How session tokens are looked up from their variables.
+    env: fn(&str) -> Option<String>,
+}
+
+impl Fetcher {
+    /// Create a fetcher that caches inputs in the given directory, waits at
+    /// least `interval` between requests, and rejects inputs larger than
+    /// `max_size` bytes.
+    pub fn new(cache: PathBuf, interval: Duration, max_size: u64) -> Result<Self> {
+        let client = reqwest::blocking::Client::builder()
+            // the site asks that automated tools identify themselves
+            .user_agent(format!(
+                "{}/{} ({})",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION"),
+                env!("CARGO_PKG_DESCRIPTION")
+            ))
+            .timeout(Duration::from_secs(30))
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            client,
+            cache,
+            interval,
+            max_size,
+            last_request: None,
+            env: |name| std::env::var(name).ok(),
+        })
+    }
+
+    /// The path to the cached input for the given day, fetching it first if it
+    /// is not cached, or `None` if the day does not exist yet.
+    ///
+    /// A cached input that no longer passes the checks, such as one cached by
+    /// an older version, is removed and fetched again.
+    pub fn fetch(
+        &mut self,
+        source: &InputFetch,
+        year: usize,
+        day: usize,
+    ) -> Result<Option<PathBuf>> {
+        let session = source.session(self.env)?;
+        let path = self.cache_path(source, &session, year, day);
+
+        if path.is_file() {
+            let cached = std::fs::read(&path).context("Failed to read cached input")?;
+            if self.check(&cached).is_ok() {
+                return Ok(Some(path));
+            }
+            std::fs::remove_file(&path).context("Failed to remove invalid cached input")?;
+        }
+
+        let url = source.url(year, day)?;
+
+        if let Some(last) = self.last_request {
+            let elapsed = last.elapsed();
+            if elapsed < self.interval {
+                std::thread::sleep(self.interval - elapsed);
+            }
+        }
+
+        let res = self.request(&url, &session);
+        // spaced from the end of the last request, however it went
+        self.last_request = Some(Instant::now());
+
+        let Some(contents) = res? else {
+            return Ok(None);
+        };
+        self.check(&contents)
+            .with_context(|| format!("Invalid input from {}", url))?;
+
+        // written to a temporary file first so that an interrupted write is
+        // never mistaken for a cached input
+        let dir = path.parent().ok_or_else(|| anyhow!("Invalid cache path"))?;
+        std::fs::create_dir_all(dir).context("Failed to create input cache")?;
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, &contents).context("Failed to cache input")?;
+        std::fs::rename(&partial, &path).context("Failed to cache input")?;
+
+        Ok(Some(path))
+    }
+
+    /// Request the input, returning `None` if it was not found.
+    fn request(&self, url: &Url, session: &str) -> Result<Option<Vec<u8>>> {
+        let res = self
+            .client
+            .get(url.clone())
+            .header(header::COOKIE, format!("session={}", session))
+            .send()
+            .with_context(|| format!("Failed to fetch {}", url))?;
+
+        // days that are not unlocked yet are not found
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        if !res.status().is_success() {
+            bail!("Fetching {} was unsuccessful: {}", url, res.status());
+        }
+
+        let contents = res
+            .bytes()
+            .with_context(|| format!("Failed to read {}", url))?;
+
+        Ok(Some(contents.to_vec()))
+    }
+
+    /// Check the contents the same way as a copied input, so that error pages
+    /// and the like are never cached.
+    fn check(&self, contents: &[u8]) -> Result<()> {
+        if contents.len() as u64 > self.max_size {
+            bail!(
+                "Input is {} bytes, which is larger than the maximum of {} bytes",
+                contents.len(),
+                self.max_size
+            );
+        }
+
+        inputs::decode(contents)?;
+
+        Ok(())
+    }
+
+    /// Inputs are cached per site and per session, without storing the
+    /// session token itself.
+    fn cache_path(&self, source: &InputFetch, session: &str, year: usize, day: usize) -> PathBuf {
+        let site = source.base_url.host_str().unwrap_or("unknown");
+        let account = &sha256(session.as_bytes())[..16];
+
+        self.cache
+            .join(site)
+            .join(account)
+            .join(year.to_string())
+            .join(day_directory_name(day))
+    }
+}
+
+/// The default directory to cache fetched inputs in.
+pub fn default_cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("aoc-tools")
+        .join("inputs")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        sync::{Arc, Mutex},
+    };
+
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    /// The request lines and cookies of each request, and when it was received.
+    type Requests = Arc<Mutex<Vec<(String, Instant)>>>;
+
+    /// A server that answers every request for day 1 with an input, for day 3
+    /// with an error page, and every other request with a 404, recording the
+    /// requests.
+    fn stub_server() -> (Url, Requests) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::default()));
+
+        let seen = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request = String::default();
+                let mut received = None;
+                loop {
+                    let mut line = String::default();
+                    reader.read_line(&mut line).unwrap();
+                    received.get_or_insert_with(Instant::now);
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    let lower = line.to_lowercase();
+                    if lower.starts_with("get") || lower.starts_with("cookie") {
+                        request.push_str(line.trim());
+                        request.push(' ');
+                    }
+                }
+
+                let response = if request.contains("/day/1/input") {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\n1\n2\n"
+                } else if request.contains("/day/3/input") {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 15\r\nConnection: close\r\n\r\n<!DOCTYPE html>"
+                } else {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                };
+                seen.lock().unwrap().push((request, received.unwrap()));
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn env(name: &str) -> Option<String> {
+        (name == "SESSION").then(|| "abc123".to_string())
+    }
+
+    #[test]
+    fn fetch() {
+        let (base_url, requests) = stub_server();
+        let cache = TempDir::new().unwrap();
+        let source = InputFetch {
+            base_url,
+            session_env: "SESSION".into(),
+        };
+
+        let interval = Duration::from_millis(200);
+        let mut fetcher = Fetcher::new(cache.path().to_path_buf(), interval, 1024).unwrap();
+        fetcher.env = env;
+
+        let path = fetcher.fetch(&source, 2023, 1).unwrap().unwrap();
+        assert!(path.starts_with(cache.path()));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1\n2\n");
+
+        // cached inputs are never fetched again
+        assert_eq!(fetcher.fetch(&source, 2023, 1).unwrap(), Some(path.clone()));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // but requests that do reach the server are spaced out
+        assert_eq!(fetcher.fetch(&source, 2023, 2).unwrap(), None);
+
+        {
+            let requests = requests.lock().unwrap();
+            assert_eq!(requests.len(), 2);
+            assert!(requests[0].0.starts_with("GET /2023/day/1/input"));
+            assert!(
+                requests[0].0.contains("session=abc123"),
+                "{}",
+                requests[0].0
+            );
+            assert!(requests[1].1 - requests[0].1 >= interval);
+        }
+
+        // invalid inputs are not cached
+        let invalid = fetcher.cache_path(&source, "abc123", 2023, 3);
+        assert!(fetcher.fetch(&source, 2023, 3).is_err());
+        assert!(!invalid.exists());
+
+        // and invalid cached inputs are fetched again
+        std::fs::write(&path, "<html>").unwrap();
+        assert_eq!(fetcher.fetch(&source, 2023, 1).unwrap(), Some(path.clone()));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1\n2\n");
+        assert_eq!(requests.lock().unwrap().len(), 4);
+
+        let missing = InputFetch {
+            base_url: source.base_url.clone(),
+            session_env: "MISSING".into(),
+        };
+        assert!(fetcher.fetch(&missing, 2023, 1).is_err());
+    }
+
+    #[test]
+    fn url() {
+        let source = InputFetch {
+            base_url: Url::parse("https://adventofcode.com/").unwrap(),
+            session_env: "AOC_SESSION".into(),
+        };
+        assert_eq!(
+            source.url(2023, 5).unwrap().as_str(),
+            "https://adventofcode.com/2023/day/5/input"
+        );
+    }
+}
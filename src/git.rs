@@ -0,0 +1,447 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// The version of a participant's code that produced a result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revision {
+    /// The full hash of the checked out commit.
+    pub commit: String,
+    /// Whether there were uncommitted changes.
+    pub dirty: bool,
+}
+
+impl std::fmt::Display for Revision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let short = self.commit.get(..12).unwrap_or(&self.commit);
+        if self.dirty {
+            write!(f, "{} (dirty)", short)
+        } else {
+            write!(f, "{}", short)
+        }
+    }
+}
+
+/// The revision checked out in the given directory, or `None` if it is not a
+/// git repo of its own, even if it is inside of another repo.
+pub fn revision(location: &Path) -> Result<Option<Revision>> {
+    if !is_repo(location) {
+        return Ok(None);
+    }
+
+    Ok(Some(Revision {
+        commit: head(location)?,
+        dirty: is_dirty(location)?,
+    }))
+}
+
+/// Run git in the given directory, returning the trimmed stdout.
+fn git(location: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(location)
+        // never wait on a credential prompt for an unreachable repo
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .context("Failed to execute git")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // git is chatty, so only keep the actual errors if there are any
+        let errors: Vec<_> = stderr
+            .lines()
+            .filter(|l| l.starts_with("fatal:") || l.starts_with("error:"))
+            .collect();
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            if errors.is_empty() {
+                stderr.trim().to_string()
+            } else {
+                errors.join("; ")
+            }
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Whether the given directory is the top of its own git work tree.
+///
+/// A directory somewhere inside of another repo's work tree is not a repo of
+/// its own, and must never be mistaken for one.
+pub fn is_repo(location: &Path) -> bool {
+    let Ok(top) = git(location, &["rev-parse", "--show-toplevel"]) else {
+        return false;
+    };
+
+    match (Path::new(&top).canonicalize(), location.canonicalize()) {
+        (Ok(top), Ok(location)) => top == location,
+        _ => false,
+    }
+}
+
+/// The full hash of the commit checked out in the given directory.
+pub fn head(location: &Path) -> Result<String> {
+    git(location, &["rev-parse", "HEAD"])
+}
+
+/// Whether the work tree in the given directory has uncommitted changes,
+/// including untracked files.
+pub fn is_dirty(location: &Path) -> Result<bool> {
+    Ok(!git(location, &["status", "--porcelain"])?.is_empty())
+}
+
+/// The path to the git directory of the repo in the given directory.
+pub fn git_dir(location: &Path) -> Result<PathBuf> {
+    let raw = git(location, &["rev-parse", "--absolute-git-dir"])?;
+    Ok(PathBuf::from(raw))
+}
+
+/// The result of syncing a repo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Synced {
+    /// Whether the repo had to be cloned.
+    pub cloned: bool,
+    /// The commit that was checked out before syncing, if any.
+    pub previous: Option<String>,
+    /// The commit that is now checked out.
+    pub current: String,
+}
+
+/// Clone or fetch the repo at `url` into `location` and check out the given
+/// ref, which may be a branch, tag, or commit.
+///
+/// Without a ref, the default branch of the remote is checked out. The
+/// checkout is always detached, and a work tree with uncommitted changes is
+/// never touched.
+///
+/// A missing or empty `location` inside of another repo gets a clone of its
+/// own, and that other repo is never touched.
+pub fn sync(url: &str, location: &Path, git_ref: Option<&str>) -> Result<Synced> {
+    let is_empty_dir = location.is_dir() && location.read_dir()?.next().is_none();
+
+    let (cloned, previous) = if !location.exists() || is_empty_dir {
+        let parent = match location.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        // the clone runs in the parent, so it only needs the final component
+        let Some(name) = location.file_name() else {
+            bail!("'{}' does not name a directory", location.display());
+        };
+        std::fs::create_dir_all(parent).context("Failed to create parent directory")?;
+        git(
+            parent,
+            &["clone", "--no-checkout", "--", url, &name.to_string_lossy()],
+        )?;
+
+        (true, None)
+    } else {
+        if !is_repo(location) {
+            bail!("'{}' exists and is not a git repo", location.display());
+        }
+
+        if is_dirty(location)? {
+            bail!("'{}' has uncommitted changes", location.display());
+        }
+
+        // the remote may have changed in the config since the last sync
+        git(location, &["remote", "set-url", "origin", url])?;
+        git(
+            location,
+            &["fetch", "--tags", "--force", "--prune", "origin"],
+        )?;
+        // fetching never updates the remote's default branch, which may have
+        // been renamed or may differ for the new url
+        git(location, &["remote", "set-head", "origin", "--auto"])?;
+
+        (false, head(location).ok())
+    };
+
+    let target = match git_ref {
+        Some(r) => resolve(location, r)?,
+        None => git(
+            location,
+            &["rev-parse", "--verify", "refs/remotes/origin/HEAD^{commit}"],
+        )
+        .context("Failed to determine the default branch")?,
+    };
+
+    git(location, &["checkout", "--quiet", "--detach", &target])?;
+
+    Ok(Synced {
+        cloned,
+        previous,
+        current: head(location)?,
+    })
+}
+
+/// Resolve a branch, tag, or commit to a commit hash, preferring the remote's
+/// branches over any stale local ones.
+fn resolve(location: &Path, git_ref: &str) -> Result<String> {
+    if git_ref.starts_with('-') {
+        bail!("Invalid ref '{}'", git_ref);
+    }
+
+    for candidate in [
+        format!("refs/remotes/origin/{}", git_ref),
+        git_ref.to_string(),
+    ] {
+        if let Ok(commit) = git(
+            location,
+            &[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("{}^{{commit}}", candidate),
+            ],
+        ) {
+            return Ok(commit);
+        }
+    }
+
+    // commits that aren't reachable from any branch or tag have to be fetched
+    // explicitly
+    git(location, &["fetch", "origin", "--end-of-options", git_ref])
+        .with_context(|| format!("Could not find ref '{}'", git_ref))?;
+    git(location, &["rev-parse", "--verify", "FETCH_HEAD^{commit}"])
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::{TempDir, prelude::*};
+
+    use super::*;
+
+    fn run(location: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(["-c", "init.defaultBranch=main"])
+            .args(args)
+            .current_dir(location)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn commit(work: &Path, contents: &str) -> String {
+        std::fs::write(work.join("file.txt"), contents).unwrap();
+        run(work, &["add", "."]);
+        run(work, &["commit", "-qm", contents]);
+        run(work, &["push", "-q", "origin", "HEAD"]);
+        run(work, &["rev-parse", "HEAD"])
+    }
+
+    #[test]
+    fn revision_inside_another_repo() {
+        let dir = TempDir::new().unwrap();
+        let location = dir.child("participants/alice");
+        location.child("file.txt").write_str("1").unwrap();
+        run(dir.path(), &["init", "-q"]);
+        run(dir.path(), &["add", "."]);
+        run(dir.path(), &["commit", "-qm", "parent"]);
+
+        assert!(is_repo(dir.path()));
+        assert!(!is_repo(location.path()));
+        assert_eq!(revision(location.path()).unwrap(), None);
+
+        run(location.path(), &["init", "-q"]);
+        run(location.path(), &["add", "."]);
+        run(location.path(), &["commit", "-qm", "alice"]);
+        let revision = revision(location.path()).unwrap().unwrap();
+        assert_eq!(
+            revision.commit,
+            run(location.path(), &["rev-parse", "HEAD"])
+        );
+        assert!(!revision.dirty);
+    }
+
+    #[test]
+    fn sync_with_bare_repo() {
+        let dir = TempDir::new().unwrap();
+        let bare = dir.child("bare.git");
+        let work = dir.child("work");
+        let location = dir.child("participants/alice");
+        bare.create_dir_all().unwrap();
+        work.create_dir_all().unwrap();
+
+        run(bare.path(), &["init", "-q", "--bare"]);
+        run(work.path(), &["init", "-q"]);
+        run(
+            work.path(),
+            &["remote", "add", "origin", &bare.to_string_lossy()],
+        );
+
+        let first = commit(work.path(), "first");
+        run(work.path(), &["tag", "v1"]);
+        run(work.path(), &["push", "-q", "origin", "v1"]);
+        let second = commit(work.path(), "second");
+
+        let url = bare.to_string_lossy().to_string();
+
+        let res = sync(&url, location.path(), None).unwrap();
+        assert!(res.cloned);
+        assert_eq!(res.previous, None);
+        assert_eq!(res.current, second);
+
+        let res = sync(&url, location.path(), Some("v1")).unwrap();
+        assert!(!res.cloned);
+        assert_eq!(res.previous.as_deref(), Some(second.as_str()));
+        assert_eq!(res.current, first);
+
+        let third = commit(work.path(), "third");
+        let res = sync(&url, location.path(), Some("main")).unwrap();
+        assert_eq!(res.current, third);
+
+        let res = sync(&url, location.path(), Some(&second)).unwrap();
+        assert_eq!(res.current, second);
+
+        assert!(sync(&url, location.path(), Some("missing")).is_err());
+
+        // refs are never taken as options
+        let marker = dir.child("marker");
+        let upload_pack = format!("--upload-pack=touch {}", marker.to_string_lossy());
+        assert!(sync(&url, location.path(), Some(&upload_pack)).is_err());
+        assert!(!marker.exists());
+
+        // local changes are never discarded
+        location.child("file.txt").write_str("changed").unwrap();
+        assert!(sync(&url, location.path(), None).is_err());
+
+        let missing = dir.child("missing.git").to_string_lossy().to_string();
+        assert!(sync(&missing, dir.child("bob").path(), None).is_err());
+    }
+
+    #[test]
+    fn sync_after_default_branch_renamed() {
+        let dir = TempDir::new().unwrap();
+        let bare = dir.child("bare.git");
+        let work = dir.child("work");
+        let location = dir.child("participants/alice");
+        bare.create_dir_all().unwrap();
+        work.create_dir_all().unwrap();
+
+        run(bare.path(), &["init", "-q", "--bare"]);
+        run(work.path(), &["init", "-q"]);
+        run(
+            work.path(),
+            &["remote", "add", "origin", &bare.to_string_lossy()],
+        );
+        let first = commit(work.path(), "first");
+
+        let url = bare.to_string_lossy().to_string();
+        let res = sync(&url, location.path(), None).unwrap();
+        assert_eq!(res.current, first);
+
+        run(bare.path(), &["branch", "-m", "main", "trunk"]);
+        std::fs::write(work.path().join("file.txt"), "second").unwrap();
+        run(work.path(), &["commit", "-qam", "second"]);
+        run(work.path(), &["push", "-q", "origin", "HEAD:trunk"]);
+        let second = run(work.path(), &["rev-parse", "HEAD"]);
+
+        let res = sync(&url, location.path(), None).unwrap();
+        assert!(!res.cloned);
+        assert_eq!(res.current, second);
+    }
+
+    #[test]
+    fn sync_with_relative_location() {
+        let dir = TempDir::new().unwrap();
+        let bare = dir.child("bare.git");
+        let work = dir.child("work");
+        bare.create_dir_all().unwrap();
+        work.create_dir_all().unwrap();
+
+        run(bare.path(), &["init", "-q", "--bare"]);
+        run(work.path(), &["init", "-q"]);
+        run(
+            work.path(),
+            &["remote", "add", "origin", &bare.to_string_lossy()],
+        );
+        let first = commit(work.path(), "first");
+
+        // reach the temp dir relative to the current directory, as changing
+        // the current directory would affect every other test
+        let cwd = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let target = dir.path().canonicalize().unwrap();
+        let mut relative = PathBuf::new();
+        for _ in cwd.components().skip(1) {
+            relative.push("..");
+        }
+        relative.push(target.strip_prefix("/").unwrap());
+        let location = relative.join("participants/alice");
+        assert!(location.is_relative());
+
+        let url = bare.to_string_lossy().to_string();
+        let res = sync(&url, &location, None).unwrap();
+        assert!(res.cloned);
+        assert_eq!(res.current, first);
+        assert!(is_repo(dir.child("participants/alice").path()));
+        assert!(!dir.child("participants/participants").exists());
+
+        let res = sync(&url, &location, None).unwrap();
+        assert!(!res.cloned);
+        assert_eq!(res.current, first);
+    }
+
+    #[test]
+    fn sync_inside_another_repo() {
+        let dir = TempDir::new().unwrap();
+        let bare = dir.child("bare.git");
+        let work = dir.child("work");
+        let parent = dir.child("parent");
+        bare.create_dir_all().unwrap();
+        work.create_dir_all().unwrap();
+        parent.create_dir_all().unwrap();
+
+        run(bare.path(), &["init", "-q", "--bare"]);
+        run(work.path(), &["init", "-q"]);
+        run(
+            work.path(),
+            &["remote", "add", "origin", &bare.to_string_lossy()],
+        );
+        let first = commit(work.path(), "first");
+
+        run(parent.path(), &["init", "-q"]);
+        parent.child("file.txt").write_str("parent").unwrap();
+        parent
+            .child("participants/carol/notes.txt")
+            .write_str("notes")
+            .unwrap();
+        run(parent.path(), &["add", "."]);
+        run(parent.path(), &["commit", "-qm", "parent"]);
+        let parent_url = "https://example.com/parent.git";
+        run(parent.path(), &["remote", "add", "origin", parent_url]);
+        let parent_head = run(parent.path(), &["rev-parse", "HEAD"]);
+
+        let url = bare.to_string_lossy().to_string();
+        let location = parent.child("participants/alice");
+
+        // a directory with contents that is not a repo of its own is refused
+        assert!(sync(&url, parent.child("participants/carol").path(), None).is_err());
+
+        let res = sync(&url, location.path(), None).unwrap();
+        assert!(res.cloned);
+        assert_eq!(res.current, first);
+
+        let res = sync(&url, location.path(), None).unwrap();
+        assert!(!res.cloned);
+        assert_eq!(res.current, first);
+
+        assert_eq!(run(parent.path(), &["rev-parse", "HEAD"]), parent_head);
+        assert_eq!(
+            run(parent.path(), &["remote", "get-url", "origin"]),
+            parent_url
+        );
+        assert_eq!(
+            std::fs::read_to_string(parent.child("file.txt").path()).unwrap(),
+            "parent"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::bench_runner::CacheMode;
+
+/// Information about the machine on which benchmarks were run.
+///
+/// Anything that cannot be determined on the current host is left empty.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchEnv {
+    pub year: usize,
+    pub day: usize,
+    /// Seconds since the unix epoch at the start of the benchmarks.
+    pub captured_at: u64,
+    pub runner: String,
+    pub hostname: Option<String>,
+    pub cpu_model: Option<String>,
+    pub cores: Option<usize>,
+    pub kernel: Option<String>,
+    pub governor: Option<String>,
+    /// The 1, 5, and 15 minute load averages.
+    pub load_average: Option<[f64; 3]>,
+    /// The CPUs the benchmarks were pinned to, if any.
+    #[serde(default)]
+    pub pinned_cpus: Vec<usize>,
+    /// The cache mode of the benchmarks, if recorded.
+    #[serde(default)]
+    pub cache: Option<CacheMode>,
+    /// Whether startup overhead was measured, if recorded.
+    #[serde(default)]
+    pub startup: Option<bool>,
+    /// The seed the runs were shuffled with, if they were interleaved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interleave_seed: Option<u64>,
+}
+
+impl BenchEnv {
+    /// Capture the environment of the current host.
+    pub fn capture(year: usize, day: usize, runner: &str, pinned_cpus: &[usize]) -> Self {
+        let captured_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        // if pinned, the governor of the first pinned cpu is the relevant one
+        let governor_cpu = pinned_cpus.first().copied().unwrap_or_default();
+
+        Self {
+            year,
+            day,
+            captured_at,
+            runner: runner.to_string(),
+            hostname: read_trimmed("/proc/sys/kernel/hostname"),
+            cpu_model: cpu_model(),
+            cores: std::thread::available_parallelism().ok().map(|n| n.get()),
+            kernel: read_trimmed("/proc/sys/kernel/osrelease"),
+            governor: read_trimmed(format!(
+                "/sys/devices/system/cpu/cpu{}/cpufreq/scaling_governor",
+                governor_cpu
+            )),
+            load_average: load_average(),
+            pinned_cpus: pinned_cpus.to_vec(),
+            cache: None,
+            startup: None,
+            interleave_seed: None,
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).context("Failed to read bench env file")?;
+        serde_json::from_str(&contents).context("Failed to parse bench env file")
+    }
+}
+
+fn read_trimmed<P: AsRef<Path>>(path: P) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn cpu_model() -> Option<String> {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo
+        .lines()
+        .find(|l| l.starts_with("model name"))
+        .and_then(|l| l.split_once(':'))
+        .map(|(_, v)| v.trim().to_string())
+}
+
+fn load_average() -> Option<[f64; 3]> {
+    let raw = std::fs::read_to_string("/proc/loadavg").ok()?;
+    let mut parts = raw.split_whitespace().map(|p| p.parse::<f64>().ok());
+    Some([parts.next()??, parts.next()??, parts.next()??])
+}
+
+/// Restrict the calling thread, and therefore any processes it spawns, to the
+/// given CPUs.
+#[cfg(target_os = "linux")]
+pub fn pin_to_cpus(cpus: &[usize]) -> Result<()> {
+    // SAFETY: cpu_set_t is a plain C struct for which all zeroes is valid.
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+
+    for cpu in cpus.iter() {
+        if *cpu >= libc::CPU_SETSIZE as usize {
+            bail!("CPU {} is out of range", cpu);
+        }
+        // SAFETY: the cpu is within the bounds of the set.
+        unsafe { libc::CPU_SET(*cpu, &mut set) };
+    }
+
+    // SAFETY: set is valid for the duration of the call.
+    let ret = unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) };
+
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("Failed to pin to CPUs {:?}", cpus));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_to_cpus(_cpus: &[usize]) -> Result<()> {
+    bail!("CPU pinning is only supported on Linux")
+}
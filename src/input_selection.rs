@@ -0,0 +1,237 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Context, Result, bail};
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::highlight;
+
+/// The strategy used to pick which official inputs are used when benchmarking
+/// a given day.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "lowercase")]
+pub enum InputSelection {
+    /// The first N inputs, ordered by name.
+    #[default]
+    First,
+    /// Every available input, ignoring the configured maximum.
+    All,
+    /// N inputs chosen at random.
+    ///
+    /// If no seed is given, one is generated and recorded with the selection.
+    Random {
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+    /// The N largest inputs by size in bytes.
+    Largest,
+    /// An explicit list of inputs for each day, keyed by the day number.
+    ///
+    /// Days without an entry fall back to the first N inputs.
+    Explicit {
+        #[serde(default)]
+        days: BTreeMap<String, Vec<String>>,
+    },
+}
+
+/// A record of the inputs selected for a day's benchmarks.
+///
+/// This is written alongside the benchmark results so that a run can be
+/// reproduced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectedInputs {
+    pub strategy: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    pub inputs: Vec<String>,
+}
+
+impl InputSelection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::First => "first",
+            Self::All => "all",
+            Self::Random { .. } => "random",
+            Self::Largest => "largest",
+            Self::Explicit { .. } => "explicit",
+        }
+    }
+
+    /// Select at most `max` inputs from the given `available` inputs.
+    ///
+    /// The `available` inputs are the names of input files in the specified
+    /// `day_directory`. If a `seed` is given, it takes precedence over any seed
+    /// in the config. The selected inputs are returned sorted by name.
+    pub fn select(
+        &self,
+        day: usize,
+        available: &[&str],
+        day_directory: &Path,
+        max: usize,
+        seed: Option<u64>,
+    ) -> Result<SelectedInputs> {
+        let mut sorted = available.to_vec();
+        sorted.sort();
+
+        let mut seed_used = None;
+
+        let mut inputs: Vec<String> = match self {
+            Self::First => sorted.iter().take(max).map(|s| s.to_string()).collect(),
+            Self::All => sorted.iter().map(|s| s.to_string()).collect(),
+            Self::Random { seed: configured } => {
+                let seed = seed.or(*configured).unwrap_or_else(rand::random);
+                seed_used = Some(seed);
+                let mut rng = StdRng::seed_from_u64(seed);
+                sorted
+                    .choose_multiple(&mut rng, max)
+                    .map(|s| s.to_string())
+                    .collect()
+            }
+            Self::Largest => {
+                let mut sized = Vec::with_capacity(sorted.len());
+                for name in sorted.iter() {
+                    let meta = std::fs::metadata(day_directory.join(name))
+                        .with_context(|| format!("Failed to read metadata for {}", name))?;
+                    sized.push((meta.len(), *name));
+                }
+                // largest first, falling back to the name for stability
+                sized.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+                sized
+                    .into_iter()
+                    .take(max)
+                    .map(|(_, s)| s.to_string())
+                    .collect()
+            }
+            Self::Explicit { days } => match days.get(&day.to_string()) {
+                Some(wanted) => {
+                    let mut out = Vec::with_capacity(wanted.len());
+                    for name in wanted {
+                        if sorted.contains(&name.as_str()) {
+                            out.push(name.clone());
+                        } else {
+                            println!(
+                                "  {}",
+                                highlight!(format!(
+                                    "Explicitly selected input {} is not available",
+                                    name
+                                ))
+                            );
+                        }
+                    }
+
+                    if out.is_empty() {
+                        bail!(
+                            "None of the explicitly selected inputs for day {} are available",
+                            day
+                        );
+                    }
+
+                    out
+                }
+                None => sorted.iter().take(max).map(|s| s.to_string()).collect(),
+            },
+        };
+
+        inputs.sort();
+        inputs.dedup();
+
+        Ok(SelectedInputs {
+            strategy: self.name().to_string(),
+            seed: seed_used,
+            inputs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::{TempDir, prelude::*};
+    use figment::{
+        Figment,
+        providers::{Format, Toml},
+    };
+
+    use super::*;
+
+    const AVAILABLE: &[&str] = &["input-d", "input-a", "input-c", "input-b"];
+
+    #[test]
+    fn deserialize() {
+        let sel: InputSelection = Figment::new()
+            .merge(Toml::string("strategy = \"random\"\nseed = 12"))
+            .extract()
+            .unwrap();
+        assert_eq!(sel, InputSelection::Random { seed: Some(12) });
+
+        let sel: InputSelection = Figment::new()
+            .merge(Toml::string(
+                "strategy = \"explicit\"\n[days]\n3 = [\"input-a\"]",
+            ))
+            .extract()
+            .unwrap();
+        let mut days = BTreeMap::new();
+        days.insert("3".to_string(), vec!["input-a".to_string()]);
+        assert_eq!(sel, InputSelection::Explicit { days });
+    }
+
+    #[test]
+    fn first_and_all() {
+        let dir = TempDir::new().unwrap();
+
+        let sel = InputSelection::First
+            .select(1, AVAILABLE, dir.path(), 2, None)
+            .unwrap();
+        assert_eq!(sel.inputs, vec!["input-a", "input-b"]);
+
+        let sel = InputSelection::All
+            .select(1, AVAILABLE, dir.path(), 2, None)
+            .unwrap();
+        assert_eq!(sel.inputs.len(), 4);
+    }
+
+    #[test]
+    fn random_is_reproducible() {
+        let dir = TempDir::new().unwrap();
+        let strategy = InputSelection::Random { seed: None };
+
+        let first = strategy.select(1, AVAILABLE, dir.path(), 2, None).unwrap();
+        assert_eq!(first.inputs.len(), 2);
+
+        let second = strategy
+            .select(1, AVAILABLE, dir.path(), 2, first.seed)
+            .unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn largest() {
+        let dir = TempDir::new().unwrap();
+        dir.child("input-a").write_str("1").unwrap();
+        dir.child("input-b").write_str("1111").unwrap();
+        dir.child("input-c").write_str("11").unwrap();
+        dir.child("input-d").write_str("111").unwrap();
+
+        let sel = InputSelection::Largest
+            .select(1, AVAILABLE, dir.path(), 2, None)
+            .unwrap();
+        assert_eq!(sel.inputs, vec!["input-b", "input-d"]);
+    }
+
+    #[test]
+    fn explicit() {
+        let dir = TempDir::new().unwrap();
+        let mut days = BTreeMap::new();
+        days.insert(
+            "2".to_string(),
+            vec!["input-c".to_string(), "input-z".to_string()],
+        );
+        let strategy = InputSelection::Explicit { days };
+
+        let sel = strategy.select(2, AVAILABLE, dir.path(), 2, None).unwrap();
+        assert_eq!(sel.inputs, vec!["input-c"]);
+
+        // falls back to the first N
+        let sel = strategy.select(1, AVAILABLE, dir.path(), 2, None).unwrap();
+        assert_eq!(sel.inputs, vec!["input-a", "input-b"]);
+    }
+}
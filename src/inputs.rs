@@ -0,0 +1,611 @@
+//! Checks and fixes for the puzzle inputs gathered from participants, and the
+//! manifest that records what is in each day directory.
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+
+use crate::{
+    encryption::{self, read_plain},
+    util::day_directory_name,
+};
+
+const BOM: char = '\u{feff}';
+
+/// The name of the manifest in each day directory.
+pub const MANIFEST: &str = "manifest.json";
+
+/// What `copy-inputs` stored in a day directory.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Every stored input, by name.
+    #[serde(default)]
+    pub inputs: BTreeMap<String, InputRecord>,
+    /// Inputs that were identical to another input and so were not stored,
+    /// by name.
+    #[serde(default)]
+    pub aliases: BTreeMap<String, Alias>,
+}
+
+/// An input that was identical to another input, and so was not stored.
+///
+/// Its origin is kept all the same, as an anonymous name is otherwise the only
+/// record of whose input it was.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alias {
+    /// The name of the stored input with the same contents.
+    pub of: String,
+    /// The participant whose input this is.
+    pub participant: String,
+    /// The path the input was copied from.
+    pub source: PathBuf,
+}
+
+impl Manifest {
+    /// Load the manifest of the given day directory, which is empty if there
+    /// is none.
+    pub fn load(day_directory: &Path) -> Result<Self> {
+        let path = day_directory.join(MANIFEST);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        let raw = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        serde_json::from_str(&raw).with_context(|| format!("Invalid manifest '{}'", path.display()))
+    }
+
+    pub fn save(&self, day_directory: &Path) -> Result<()> {
+        let raw = serde_json::to_string_pretty(self)?;
+        std::fs::write(day_directory.join(MANIFEST), raw).context("Failed to write manifest")
+    }
+
+    /// Whether the named input is a duplicate of another input.
+    pub fn is_alias(&self, name: &str) -> bool {
+        self.aliases.contains_key(name)
+    }
+
+    /// Compare the inputs in the day directory against the manifest.
+    ///
+    /// Directories without a manifest, from before manifests were recorded,
+    /// have no problems.
+    pub fn verify(&self, day_directory: &Path) -> Result<Vec<Problem>> {
+        let mut problems = Vec::default();
+
+        if self.inputs.is_empty() {
+            return Ok(problems);
+        }
+
+        for (name, record) in self.inputs.iter() {
+            let path = day_directory.join(name);
+            if !path.is_file() {
+                problems.push(Problem::Missing(name.clone()));
+                continue;
+            }
+
+            let contents =
+                std::fs::read(&path).with_context(|| format!("Failed to read '{}'", name))?;
+            if contents.len() as u64 != record.size || sha256(&contents) != record.sha256 {
+                problems.push(Problem::Modified(name.clone()));
+            }
+
+            if let Some(ref source) = record.source
+                && is_stale(source, &record.sha256)
+            {
+                problems.push(Problem::Stale(name.clone()));
+            }
+        }
+
+        for (name, alias) in self.aliases.iter() {
+            if let Some(original) = self.inputs.get(&alias.of)
+                && is_stale(&alias.source, &original.sha256)
+            {
+                problems.push(Problem::Stale(name.clone()));
+            }
+        }
+
+        for entry in std::fs::read_dir(day_directory)? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            if InputKind::from_name(&name).is_some()
+                && !self.inputs.contains_key(&name)
+                && !self.is_alias(&name)
+            {
+                problems.push(Problem::Unlisted(name));
+            }
+        }
+
+        Ok(problems)
+    }
+}
+
+/// Whether the source of an input still exists, but no longer has the
+/// recorded contents, with or without normalization.
+fn is_stale(source: &Path, expected: &str) -> bool {
+    let Ok(raw) = std::fs::read(source) else {
+        return false;
+    };
+
+    if sha256(&raw) == expected {
+        return false;
+    }
+
+    match decode(&raw) {
+        Ok(contents) => sha256(normalize(contents).0.as_bytes()) != expected,
+        Err(_) => true,
+    }
+}
+
+/// The inputs directory with the inputs for some days in plaintext.
+///
+/// If any of those inputs is encrypted, the inputs for those days are
+/// decrypted into a temporary directory with the same layout, which is removed
+/// once this is dropped. Otherwise, the inputs directory is used as it is.
+#[derive(Debug)]
+pub struct PlainInputs {
+    path: PathBuf,
+    _temp: Option<TempDir>,
+}
+
+impl PlainInputs {
+    /// Open the inputs for the given days, leaving the inputs for every other
+    /// day encrypted.
+    pub fn open(inputs: &Path, days: impl IntoIterator<Item = usize>) -> Result<Self> {
+        let mut dirs = Vec::default();
+        let mut files = Vec::default();
+        for day in days {
+            let day_directory_name = day_directory_name(day);
+            let day_directory = inputs.join(&day_directory_name);
+            if !day_directory.is_dir() {
+                continue;
+            }
+
+            for entry in std::fs::read_dir(&day_directory)? {
+                let entry = entry?;
+                if InputKind::from_name(&entry.file_name().to_string_lossy()).is_some() {
+                    files.push((day_directory_name.clone(), entry.file_name(), entry.path()));
+                }
+            }
+            dirs.push(day_directory_name);
+        }
+
+        let mut encrypted = false;
+        for (_, _, path) in files.iter() {
+            let contents = std::fs::read(path)?;
+            if encryption::is_encrypted(&contents) {
+                encrypted = true;
+                break;
+            }
+        }
+
+        if !encrypted {
+            return Ok(Self {
+                path: inputs.to_path_buf(),
+                _temp: None,
+            });
+        }
+
+        let temp = tempfile::Builder::new()
+            .prefix("aoc-tools-inputs-")
+            .tempdir()
+            .context("Failed to create directory for decrypted inputs")?;
+
+        for day in dirs.iter() {
+            std::fs::create_dir(temp.path().join(day))?;
+        }
+
+        for (day, name, path) in files.iter() {
+            std::fs::write(temp.path().join(day).join(name), read_plain(path)?)
+                .context("Failed to write decrypted input")?;
+        }
+
+        Ok(Self {
+            path: temp.path().to_path_buf(),
+            _temp: Some(temp),
+        })
+    }
+
+    /// The directory to read the inputs from, which has the same layout as
+    /// the inputs directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Whether an input is a participant's official input or a challenge input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputKind {
+    Official,
+    Challenge,
+}
+
+impl InputKind {
+    /// The kind of input with the given file name, or `None` if it is not an
+    /// input.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.starts_with("input-") {
+            Some(Self::Official)
+        } else if name.starts_with("challenge-input") {
+            Some(Self::Challenge)
+        } else {
+            None
+        }
+    }
+}
+
+/// Where a stored input came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputRecord {
+    pub kind: InputKind,
+    /// The participant whose input this is, if any.
+    pub participant: Option<String>,
+    /// The path the input was copied from, if known.
+    pub source: Option<PathBuf>,
+    /// The hex encoded SHA-256 of the plaintext contents, before any
+    /// encryption.
+    pub sha256: String,
+    /// The size of the plaintext contents in bytes.
+    pub size: u64,
+    pub lines: u64,
+    /// Seconds since the unix epoch when the input was copied.
+    pub copied_at: u64,
+}
+
+impl InputRecord {
+    /// Record the given contents as they are being stored now.
+    pub fn new(
+        kind: InputKind,
+        participant: Option<&str>,
+        source: Option<&Path>,
+        contents: &[u8],
+    ) -> Self {
+        let copied_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            kind,
+            participant: participant.map(str::to_string),
+            source: source.map(Path::to_path_buf),
+            sha256: sha256(contents),
+            size: contents.len() as u64,
+            lines: line_count(contents),
+            copied_at,
+        }
+    }
+}
+
+/// A difference between a day directory and its manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The input's contents no longer match what was copied.
+    Modified(String),
+    /// The input is in the manifest but not the directory.
+    Missing(String),
+    /// The input is in the directory but not the manifest.
+    Unlisted(String),
+    /// The input's source has changed since it was copied.
+    Stale(String),
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Modified(name) => write!(f, "{} was modified after it was copied", name),
+            Self::Missing(name) => write!(f, "{} is in the manifest but does not exist", name),
+            Self::Unlisted(name) => write!(f, "{} is not in the manifest", name),
+            Self::Stale(name) => write!(
+                f,
+                "{} has changed at its source since it was copied, and should be copied again",
+                name
+            ),
+        }
+    }
+}
+
+/// The number of lines in the contents of an input, counting a final line
+/// without a newline.
+pub fn line_count(contents: &[u8]) -> u64 {
+    let mut lines = contents.iter().filter(|b| **b == b'\n').count() as u64;
+    if contents.last().is_some_and(|b| *b != b'\n') {
+        lines += 1;
+    }
+
+    lines
+}
+
+/// The environment variable holding the secret that `copy-inputs` salts
+/// anonymous names with.
+pub const SALT_VAR: &str = "AOC_TOOLS_INPUT_SALT";
+
+/// The anonymous name of a participant's input for a day, such as
+/// `input-a3f9`, using `len` hex digits of a hash of their username salted
+/// with a secret.
+///
+/// Without the salt, the name cannot be traced back to the participant by
+/// hashing every username.
+pub fn anonymous_name(salt: &str, day: usize, username: &str, len: usize) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(day.to_string().as_bytes());
+    hasher.update(b"\0");
+    hasher.update(username.as_bytes());
+    let digest = hex::encode(hasher.finalize());
+
+    format!("input-{}", &digest[..len.min(digest.len())])
+}
+
+/// The hex encoded SHA-256 of the contents of an input.
+pub fn sha256(contents: &[u8]) -> String {
+    hex::encode(Sha256::digest(contents))
+}
+
+/// A change made to an input by [normalize].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    StrippedBom,
+    /// The number of CRLF line endings converted to LF.
+    ConvertedLineEndings(usize),
+    AddedTrailingNewline,
+    /// The number of extra trailing newlines removed.
+    RemovedTrailingNewlines(usize),
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::StrippedBom => write!(f, "stripped UTF-8 byte order mark"),
+            Self::ConvertedLineEndings(n) => write!(f, "converted {} CRLF line ending(s) to LF", n),
+            Self::AddedTrailingNewline => write!(f, "added trailing newline"),
+            Self::RemovedTrailingNewlines(n) => {
+                write!(f, "removed {} extra trailing newline(s)", n)
+            }
+        }
+    }
+}
+
+/// Interpret the raw contents of an input, rejecting anything that is not
+/// UTF-8 or that looks like an HTML page rather than a puzzle input.
+///
+/// HTML usually means that the input was downloaded without being logged in,
+/// or while the site was having trouble.
+pub fn decode(raw: &[u8]) -> Result<&str> {
+    let Ok(contents) = std::str::from_utf8(raw) else {
+        bail!("Input is not valid UTF-8");
+    };
+
+    if looks_like_html(contents) {
+        bail!("Input looks like an HTML page, not a puzzle input");
+    }
+
+    Ok(contents)
+}
+
+fn looks_like_html(contents: &str) -> bool {
+    let start: String = contents
+        .trim_start_matches(BOM)
+        .trim_start()
+        .chars()
+        .take(64)
+        .collect::<String>()
+        .to_lowercase();
+
+    start.starts_with("<!doctype html") || start.starts_with("<html")
+}
+
+/// Strip any byte order mark, convert CRLF line endings to LF, and make the
+/// input end in exactly one newline, returning the result and what changed.
+pub fn normalize(contents: &str) -> (String, Vec<Change>) {
+    let mut changes = Vec::default();
+
+    let mut out = match contents.strip_prefix(BOM) {
+        Some(rest) => {
+            changes.push(Change::StrippedBom);
+            rest
+        }
+        None => contents,
+    }
+    .to_string();
+
+    let crlf = out.matches("\r\n").count();
+    if crlf > 0 {
+        out = out.replace("\r\n", "\n");
+        changes.push(Change::ConvertedLineEndings(crlf));
+    }
+
+    let trimmed = out.trim_end_matches('\n').len();
+    match out.len() - trimmed {
+        0 => {
+            out.push('\n');
+            changes.push(Change::AddedTrailingNewline);
+        }
+        1 => {}
+        n => {
+            out.truncate(trimmed + 1);
+            changes.push(Change::RemovedTrailingNewlines(n - 1));
+        }
+    }
+
+    (out, changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn manifest() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(Manifest::load(dir.path()).unwrap(), Manifest::default());
+
+        let mut manifest = Manifest::default();
+        manifest.aliases.insert(
+            "input-bob".into(),
+            Alias {
+                of: "input-alice".into(),
+                participant: "bob".into(),
+                source: "/bob/input.txt".into(),
+            },
+        );
+        manifest.save(dir.path()).unwrap();
+
+        let loaded = Manifest::load(dir.path()).unwrap();
+        assert_eq!(loaded, manifest);
+        assert!(loaded.is_alias("input-bob"));
+        assert!(!loaded.is_alias("input-alice"));
+    }
+
+    #[test]
+    fn anonymous_alias() {
+        let dir = TempDir::new().unwrap();
+        let alice = super::anonymous_name("salt", 1, "alice", 4);
+        let bob = super::anonymous_name("salt", 1, "bob", 4);
+
+        let mut manifest = Manifest::default();
+        manifest.inputs.insert(
+            alice.clone(),
+            InputRecord::new(
+                InputKind::Official,
+                Some("alice"),
+                Some(Path::new("/alice/input.txt")),
+                b"1\n2\n",
+            ),
+        );
+        manifest.aliases.insert(
+            bob.clone(),
+            Alias {
+                of: alice.clone(),
+                participant: "bob".into(),
+                source: "/bob/input.txt".into(),
+            },
+        );
+        manifest.save(dir.path()).unwrap();
+
+        // the manifest is the only way back from an anonymous name
+        let loaded = Manifest::load(dir.path()).unwrap();
+        assert_eq!(loaded, manifest);
+        assert_eq!(loaded.aliases[&bob].participant, "bob");
+        assert_eq!(loaded.aliases[&bob].source, Path::new("/bob/input.txt"));
+        assert_eq!(
+            loaded.inputs[&loaded.aliases[&bob].of]
+                .participant
+                .as_deref(),
+            Some("alice")
+        );
+    }
+
+    #[test]
+    fn verify() {
+        let dir = TempDir::new().unwrap();
+        let mut manifest = Manifest::default();
+        for name in ["input-alice", "input-carol", "challenge-input-big"] {
+            std::fs::write(dir.path().join(name), "1\n2\n").unwrap();
+            let kind = InputKind::from_name(name).unwrap();
+            manifest
+                .inputs
+                .insert(name.into(), InputRecord::new(kind, None, None, b"1\n2\n"));
+        }
+        manifest.aliases.insert(
+            "input-bob".into(),
+            Alias {
+                of: "input-alice".into(),
+                participant: "bob".into(),
+                source: "/bob/input.txt".into(),
+            },
+        );
+
+        assert_eq!(manifest.inputs["input-alice"].lines, 2);
+        assert_eq!(
+            manifest.inputs["challenge-input-big"].kind,
+            InputKind::Challenge
+        );
+        assert!(manifest.verify(dir.path()).unwrap().is_empty());
+
+        // sources that still match, including after normalization, are fine
+        let sources = dir.path().join("sources");
+        std::fs::create_dir(&sources).unwrap();
+        std::fs::write(sources.join("alice.txt"), "1\r\n2").unwrap();
+        std::fs::write(sources.join("bob.txt"), "1\n2\n").unwrap();
+        manifest.inputs.get_mut("input-alice").unwrap().source = Some(sources.join("alice.txt"));
+        manifest.aliases.get_mut("input-bob").unwrap().source = sources.join("bob.txt");
+        manifest.inputs.get_mut("input-carol").unwrap().source = Some(sources.join("carol.txt"));
+        assert!(manifest.verify(dir.path()).unwrap().is_empty());
+
+        std::fs::write(sources.join("alice.txt"), "5\n6\n").unwrap();
+        std::fs::write(sources.join("bob.txt"), "7\n").unwrap();
+        assert_eq!(
+            manifest.verify(dir.path()).unwrap(),
+            vec![
+                Problem::Stale("input-alice".into()),
+                Problem::Stale("input-bob".into()),
+            ]
+        );
+        std::fs::write(sources.join("alice.txt"), "1\n2\n").unwrap();
+        std::fs::write(sources.join("bob.txt"), "1\n2\n").unwrap();
+
+        std::fs::write(dir.path().join("input-alice"), "1\n3\n").unwrap();
+        std::fs::remove_file(dir.path().join("input-carol")).unwrap();
+        std::fs::write(dir.path().join("input-dave"), "4\n").unwrap();
+        std::fs::write(dir.path().join("solutions.json"), "{}").unwrap();
+
+        assert_eq!(
+            manifest.verify(dir.path()).unwrap(),
+            vec![
+                Problem::Modified("input-alice".into()),
+                Problem::Missing("input-carol".into()),
+                Problem::Unlisted("input-dave".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn anonymous_name() {
+        let name = super::anonymous_name("salt", 1, "alice", 4);
+        assert_eq!(name.len(), "input-".len() + 4);
+        assert_eq!(name, super::anonymous_name("salt", 1, "alice", 4));
+        assert!(super::anonymous_name("salt", 1, "alice", 8).starts_with(&name));
+        assert_ne!(name, super::anonymous_name("other", 1, "alice", 4));
+        assert_ne!(name, super::anonymous_name("salt", 2, "alice", 4));
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(super::decode(b"1\n2\n").unwrap(), "1\n2\n");
+        assert!(super::decode(&[0x31, 0xff, 0x0a]).is_err());
+        assert!(super::decode(b"<!DOCTYPE html>\n<html></html>\n").is_err());
+        assert!(super::decode("\u{feff}  <html lang=\"en\">".as_bytes()).is_err());
+        assert!(super::decode(b"<x=1, y=2>\n").is_ok());
+    }
+
+    #[test]
+    fn normalize() {
+        let (out, changes) = super::normalize("1\n2\n");
+        assert_eq!(out, "1\n2\n");
+        assert!(changes.is_empty());
+
+        let (out, changes) = super::normalize("\u{feff}1\r\n2\r\n\r\n");
+        assert_eq!(out, "1\n2\n");
+        assert_eq!(
+            changes,
+            vec![
+                Change::StrippedBom,
+                Change::ConvertedLineEndings(3),
+                Change::RemovedTrailingNewlines(1)
+            ]
+        );
+
+        let (out, changes) = super::normalize("1\n2");
+        assert_eq!(out, "1\n2\n");
+        assert_eq!(changes, vec![Change::AddedTrailingNewline]);
+    }
+}
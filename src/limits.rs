@@ -0,0 +1,394 @@
+//! Resource limits for participants' processes.
+//!
+//! Limits are applied with `setrlimit` to every solver and benchmark process,
+//! and are inherited by anything those processes spawn. Each limit applies to
+//! each process individually, except for the process count, which the kernel
+//! counts across every process of the user running the tools.
+use std::{
+    os::unix::process::{CommandExt, ExitStatusExt},
+    process::{Command, ExitStatus},
+};
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use which::which;
+
+const MIB: u64 = 1024 * 1024;
+
+/// The resource limits for a participant's processes. Unset limits are left
+/// as they are.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Limits {
+    /// The maximum size of the virtual address space, in MiB.
+    ///
+    /// This is much larger than the memory used by runtimes that reserve
+    /// address space up front, such as the JVM, Go, and V8.
+    #[serde(default)]
+    pub address_space_mib: Option<u64>,
+    /// The maximum CPU time, in seconds.
+    #[serde(default)]
+    pub cpu_seconds: Option<u64>,
+    /// The maximum number of open file descriptors.
+    #[serde(default)]
+    pub open_files: Option<u64>,
+    /// The maximum number of processes and threads of the user.
+    #[serde(default)]
+    pub processes: Option<u64>,
+    /// The maximum size of any file written, in MiB.
+    #[serde(default)]
+    pub file_size_mib: Option<u64>,
+}
+
+/// A resource that can be limited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    AddressSpace,
+    CpuTime,
+    OpenFiles,
+    Processes,
+    FileSize,
+}
+
+impl Resource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::AddressSpace => "address space",
+            Self::CpuTime => "CPU time",
+            Self::OpenFiles => "open files",
+            Self::Processes => "process count",
+            Self::FileSize => "file size",
+        }
+    }
+
+    fn unit(&self) -> &'static str {
+        match self {
+            Self::AddressSpace | Self::FileSize => " MiB",
+            Self::CpuTime => " s",
+            Self::OpenFiles | Self::Processes => "",
+        }
+    }
+}
+
+/// The error produced when a process was stopped by, or failed because of, one
+/// of its limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitExceeded {
+    pub resource: Resource,
+    /// The configured limit, in the units of the config.
+    pub limit: u64,
+    /// Whether the kernel signalled that the limit was exceeded, rather than
+    /// the process printing something that suggests it was.
+    pub certain: bool,
+}
+
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} the {} limit of {}{}",
+            if self.certain {
+                "Exceeded"
+            } else {
+                "Possibly exceeded"
+            },
+            self.resource.name(),
+            self.limit,
+            self.resource.unit()
+        )
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// The messages that processes commonly print when a limit makes a system call
+/// fail, rather than the kernel sending a signal. Matched case-insensitively.
+///
+/// Processes can print these for other reasons, such as a panic message that
+/// mentions memory, so a match only means a limit was possibly exceeded.
+const STDERR_HINTS: &[(Resource, &[&str])] = &[
+    (
+        Resource::AddressSpace,
+        &[
+            "memory allocation of",
+            "out of memory",
+            "outofmemory",
+            "cannot allocate memory",
+            "memoryerror",
+            "bad_alloc",
+            "failed to allocate",
+        ],
+    ),
+    (Resource::OpenFiles, &["too many open files"]),
+    (
+        Resource::Processes,
+        &["resource temporarily unavailable", "cannot fork"],
+    ),
+];
+
+impl Limits {
+    /// These limits, with any unset limit taken from the `defaults`.
+    pub fn or(self, defaults: Limits) -> Limits {
+        Limits {
+            address_space_mib: self.address_space_mib.or(defaults.address_space_mib),
+            cpu_seconds: self.cpu_seconds.or(defaults.cpu_seconds),
+            open_files: self.open_files.or(defaults.open_files),
+            processes: self.processes.or(defaults.processes),
+            file_size_mib: self.file_size_mib.or(defaults.file_size_mib),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rlimits().is_empty()
+    }
+
+    /// The configured limit for the resource, in the units of the config.
+    pub fn get(&self, resource: Resource) -> Option<u64> {
+        match resource {
+            Resource::AddressSpace => self.address_space_mib,
+            Resource::CpuTime => self.cpu_seconds,
+            Resource::OpenFiles => self.open_files,
+            Resource::Processes => self.processes,
+            Resource::FileSize => self.file_size_mib,
+        }
+    }
+
+    /// The `(resource, soft, hard)` limits to set, in the units of the kernel.
+    ///
+    /// The hard CPU limit is a second past the soft limit, so that the process
+    /// receives `SIGXCPU` instead of `SIGKILL`, which is indistinguishable from
+    /// being killed for any other reason.
+    fn rlimits(&self) -> Vec<(Resource, u64, u64)> {
+        let mut out = Vec::default();
+
+        if let Some(v) = self.address_space_mib {
+            out.push((Resource::AddressSpace, v * MIB, v * MIB));
+        }
+        if let Some(v) = self.cpu_seconds {
+            out.push((Resource::CpuTime, v, v + 1));
+        }
+        if let Some(v) = self.open_files {
+            out.push((Resource::OpenFiles, v, v));
+        }
+        if let Some(v) = self.processes {
+            out.push((Resource::Processes, v, v));
+        }
+        if let Some(v) = self.file_size_mib {
+            out.push((Resource::FileSize, v * MIB, v * MIB));
+        }
+
+        out
+    }
+
+    /// Apply the limits to the process spawned by the command.
+    ///
+    /// Limits above the current hard limits are lowered to them, as they
+    /// cannot be raised without privileges.
+    pub fn apply(&self, cmd: &mut Command) {
+        if self.is_empty() {
+            return;
+        }
+
+        // everything the child needs is allocated before forking
+        let rlimits: Vec<_> = self
+            .rlimits()
+            .into_iter()
+            .map(|(resource, soft, hard)| {
+                (
+                    resource_id(resource),
+                    soft as libc::rlim_t,
+                    hard as libc::rlim_t,
+                )
+            })
+            .collect();
+
+        // SAFETY: the closure only makes raw syscalls on memory allocated
+        // before the fork.
+        unsafe {
+            cmd.pre_exec(move || {
+                for (resource, soft, hard) in rlimits.iter() {
+                    let mut current = libc::rlimit {
+                        rlim_cur: 0,
+                        rlim_max: 0,
+                    };
+                    if libc::getrlimit(*resource, &mut current) < 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+
+                    let limit = libc::rlimit {
+                        rlim_cur: (*soft).min(current.rlim_max),
+                        rlim_max: (*hard).min(current.rlim_max),
+                    };
+                    if libc::setrlimit(*resource, &limit) < 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+
+                Ok(())
+            });
+        }
+    }
+
+    /// The `prlimit` invocation to prefix a shell command with to apply the
+    /// limits, for runners that take commands as strings.
+    pub fn prefix(&self) -> Result<Option<String>> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+
+        let Ok(prlimit) = which("prlimit") else {
+            bail!("prlimit must be installed to apply resource limits to shell commands");
+        };
+
+        let mut args = vec![prlimit.to_string_lossy().to_string()];
+        for (resource, soft, hard) in self.rlimits() {
+            let flag = match resource {
+                Resource::AddressSpace => "--as",
+                Resource::CpuTime => "--cpu",
+                Resource::OpenFiles => "--nofile",
+                Resource::Processes => "--nproc",
+                Resource::FileSize => "--fsize",
+            };
+            args.push(format!("{}={}:{}", flag, soft, hard));
+        }
+        args.push("--".to_string());
+
+        Ok(Some(shell_words::join(args)))
+    }
+
+    /// Determine whether a process that exited unsuccessfully did so because
+    /// of one of its limits, based on how it exited and what it printed to
+    /// stderr.
+    ///
+    /// Only the CPU time and file size limits are signalled by the kernel, so
+    /// only those are certain. Anything else is only possibly the cause, based
+    /// on what the process printed.
+    ///
+    /// Exit codes above 128 are treated as the signal they conventionally
+    /// represent, since wrappers such as shells and `timeout` exit with them
+    /// when their command is killed. Only limits that are set are considered.
+    pub fn exceeded(&self, status: &ExitStatus, stderr: &[u8]) -> Option<LimitExceeded> {
+        if status.success() {
+            return None;
+        }
+
+        let exceeded = |resource, certain| {
+            self.get(resource).map(|limit| LimitExceeded {
+                resource,
+                limit,
+                certain,
+            })
+        };
+
+        let signal = status
+            .signal()
+            .or_else(|| status.code().filter(|c| *c > 128).map(|c| c - 128));
+
+        match signal {
+            Some(libc::SIGXCPU) => return exceeded(Resource::CpuTime, true),
+            Some(libc::SIGXFSZ) => return exceeded(Resource::FileSize, true),
+            _ => {}
+        }
+
+        let stderr = String::from_utf8_lossy(stderr).to_lowercase();
+        STDERR_HINTS
+            .iter()
+            .filter(|(_, hints)| hints.iter().any(|h| stderr.contains(h)))
+            .find_map(|(resource, _)| exceeded(*resource, false))
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type ResourceId = libc::__rlimit_resource_t;
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+type ResourceId = libc::c_int;
+
+fn resource_id(resource: Resource) -> ResourceId {
+    match resource {
+        Resource::AddressSpace => libc::RLIMIT_AS,
+        Resource::CpuTime => libc::RLIMIT_CPU,
+        Resource::OpenFiles => libc::RLIMIT_NOFILE,
+        Resource::Processes => libc::RLIMIT_NPROC,
+        Resource::FileSize => libc::RLIMIT_FSIZE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    fn run(limits: &Limits, script: &str) -> std::process::Output {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        limits.apply(&mut cmd);
+        cmd.output().unwrap()
+    }
+
+    #[test]
+    fn or() {
+        let limits = Limits {
+            cpu_seconds: Some(5),
+            ..Default::default()
+        };
+        let defaults = Limits {
+            cpu_seconds: Some(10),
+            open_files: Some(64),
+            ..Default::default()
+        };
+
+        let merged = limits.or(defaults);
+        assert_eq!(merged.cpu_seconds, Some(5));
+        assert_eq!(merged.open_files, Some(64));
+        assert_eq!(merged.address_space_mib, None);
+        assert!(Limits::default().is_empty());
+    }
+
+    #[test]
+    fn apply_and_detect() {
+        let limits = Limits {
+            open_files: Some(32),
+            cpu_seconds: Some(1),
+            file_size_mib: Some(1),
+            ..Default::default()
+        };
+
+        let out = run(&limits, "ulimit -n");
+        assert_eq!(String::from_utf8_lossy(&out.stdout).trim(), "32");
+
+        let out = run(&limits, "while :; do :; done");
+        assert_eq!(
+            limits
+                .exceeded(&out.status, &out.stderr)
+                .map(|e| e.resource),
+            Some(Resource::CpuTime)
+        );
+
+        let dir = TempDir::new().unwrap();
+        let script = format!(
+            "head -c 2097152 /dev/zero > {}/big; exit $?",
+            dir.path().display()
+        );
+        let out = run(&limits, &script);
+        assert_eq!(
+            limits.exceeded(&out.status, &out.stderr),
+            Some(LimitExceeded {
+                resource: Resource::FileSize,
+                limit: 1,
+                certain: true,
+            })
+        );
+
+        // what a process prints is only a hint
+        let out = run(&limits, "echo 'sh: Too many open files' >&2; exit 1");
+        let exceeded = limits.exceeded(&out.status, &out.stderr).unwrap();
+        assert_eq!(exceeded.resource, Resource::OpenFiles);
+        assert!(!exceeded.certain);
+        assert!(exceeded.to_string().starts_with("Possibly exceeded"));
+
+        // limits that are not set are never blamed
+        let out = run(&limits, "echo 'out of memory' >&2; exit 1");
+        assert_eq!(limits.exceeded(&out.status, &out.stderr), None);
+    }
+}
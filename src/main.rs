@@ -4,9 +4,21 @@ use cli::Cli;
 
 mod aoc_project;
 mod bench_data;
+mod bench_runner;
 mod cli;
 mod config;
+mod encryption;
+mod fetch;
+mod git;
+mod host;
+mod input_selection;
+mod inputs;
+mod limits;
+mod regression;
+mod sandbox;
+mod session;
 mod solution;
+mod stats;
 mod util;
 
 fn main() -> Result<()> {
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use crate::{bench_data::BenchSamples, bench_runner::CacheMode, stats};
+
+/// The outcome of comparing a benchmark against its previous results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Regression,
+    Improvement,
+    NoChange,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Regression => "regression",
+            Self::Improvement => "improvement",
+            Self::NoChange => "no change",
+        }
+    }
+}
+
+/// A comparison of the current and previous samples of a single participant
+/// and input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub participant: String,
+    pub input: String,
+    pub part: Option<u8>,
+    /// The previous mean in seconds.
+    pub previous: f64,
+    /// The current mean in seconds.
+    pub current: f64,
+    /// The percent change of the mean, where positive is slower.
+    pub change: f64,
+    pub p_value: Option<f64>,
+    pub verdict: Verdict,
+}
+
+impl Comparison {
+    /// Whether this is a significant slowdown of more than `threshold` percent.
+    pub fn exceeds(&self, threshold: f64) -> bool {
+        self.verdict == Verdict::Regression && self.change > threshold
+    }
+}
+
+/// Compare every set of current samples with a matching set of previous
+/// samples, using the Mann-Whitney U test with the given significance level.
+///
+/// Samples only match if they are of the same participant, input, and part,
+/// and were taken by the same runner with the same cache state, as every
+/// runner and cache state measures differently.
+pub fn compare(previous: &[BenchSamples], current: &[BenchSamples], alpha: f64) -> Vec<Comparison> {
+    let lookup: HashMap<Key, &BenchSamples> = previous.iter().map(|s| (key(s), s)).collect();
+
+    let mut out = Vec::default();
+
+    for cur in current.iter() {
+        let Some(prev) = lookup.get(&key(cur)) else {
+            continue;
+        };
+
+        let previous = stats::mean(&prev.times);
+        let current = stats::mean(&cur.times);
+
+        if previous <= 0.0 {
+            continue;
+        }
+
+        let change = (current - previous) / previous * 100.0;
+        let p_value = stats::mann_whitney_u(&prev.times, &cur.times);
+
+        let verdict = match p_value {
+            Some(p) if p < alpha && current > previous => Verdict::Regression,
+            Some(p) if p < alpha && current < previous => Verdict::Improvement,
+            _ => Verdict::NoChange,
+        };
+
+        out.push(Comparison {
+            participant: cur.participant.clone(),
+            input: cur.input.clone(),
+            part: cur.part,
+            previous,
+            current,
+            change,
+            p_value,
+            verdict,
+        });
+    }
+
+    out.sort_by(|a, b| {
+        a.participant
+            .cmp(&b.participant)
+            .then(a.input.cmp(&b.input))
+            .then(a.part.cmp(&b.part))
+    });
+
+    out
+}
+
+/// What makes two sets of samples comparable.
+type Key<'a> = (&'a str, &'a str, &'a str, Option<u8>, CacheMode);
+
+fn key(samples: &BenchSamples) -> Key<'_> {
+    (
+        samples.participant.as_str(),
+        samples.input.as_str(),
+        samples.runner.as_str(),
+        samples.part,
+        samples.cache,
+    )
+}
+
+/// Render the comparisons as a markdown table.
+pub fn markdown_table(comparisons: &[Comparison]) -> String {
+    let mut lines = vec![
+        "| Participant | Input | Previous [ms] | Current [ms] | Change | p-value | Result |"
+            .to_string(),
+        "|:---|:---|---:|---:|---:|---:|:---|".to_string(),
+    ];
+
+    for c in comparisons.iter() {
+        lines.push(format!(
+            "| {} | {} | {:.1} | {:.1} | {:+.1}% | {} | {} |",
+            c.participant,
+            match c.part {
+                Some(part) => format!("{} (part {})", c.input, part),
+                None => c.input.clone(),
+            },
+            c.previous * 1000.0,
+            c.current * 1000.0,
+            c.change,
+            c.p_value
+                .map(|p| format!("{:.3}", p))
+                .unwrap_or_else(|| "-".to_string()),
+            c.verdict.as_str(),
+        ));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(participant: &str, times: &[f64]) -> BenchSamples {
+        BenchSamples {
+            participant: participant.into(),
+            input: "input-a".into(),
+            runner: "native".into(),
+            part: None,
+            cache: CacheMode::Warm,
+            times: times.to_vec(),
+        }
+    }
+
+    #[test]
+    fn compare() {
+        let previous = vec![
+            samples("slower", &[1.0, 1.1, 0.9, 1.0, 1.05, 0.95]),
+            samples("faster", &[1.0, 1.1, 0.9, 1.0, 1.05, 0.95]),
+            samples("same", &[1.0, 1.1, 0.9, 1.0, 1.05, 0.95]),
+        ];
+        let current = vec![
+            samples("slower", &[2.0, 2.1, 1.9, 2.0, 2.05, 1.95]),
+            samples("faster", &[0.5, 0.55, 0.45, 0.5, 0.52, 0.48]),
+            samples("same", &[1.0, 1.05, 0.95, 1.1, 0.9, 1.0]),
+            samples("new", &[1.0]),
+        ];
+
+        let res = super::compare(&previous, &current, 0.05);
+        assert_eq!(res.len(), 3);
+
+        assert_eq!(res[0].participant, "faster");
+        assert_eq!(res[0].verdict, Verdict::Improvement);
+        assert_eq!(res[1].participant, "same");
+        assert_eq!(res[1].verdict, Verdict::NoChange);
+        assert_eq!(res[2].participant, "slower");
+        assert_eq!(res[2].verdict, Verdict::Regression);
+        assert!((res[2].change - 100.0).abs() < 1e-9);
+
+        assert!(res[2].exceeds(50.0));
+        assert!(!res[2].exceeds(150.0));
+        assert!(!res[0].exceeds(0.0));
+
+        // samples from another runner are never compared
+        let mut other = samples("slower", &[2.0, 2.1, 1.9, 2.0, 2.05, 1.95]);
+        other.runner = "hyperfine".into();
+        assert!(super::compare(&previous, &[other], 0.05).is_empty());
+    }
+}
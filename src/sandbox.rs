@@ -0,0 +1,199 @@
+//! Isolation of participants' processes from the host.
+//!
+//! Processes are sandboxed with bubblewrap, which must be installed. Only the
+//! system directories, the participant's location, and the given paths are
+//! visible, all read-only, with a private `/tmp` and no network. Nothing else
+//! on the host, such as other participants' code and inputs, can be read.
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::OnceLock,
+};
+
+use which::which;
+
+use crate::highlight;
+
+/// System directories made visible by bubblewrap, if they exist.
+const SYSTEM_PATHS: &[&str] = &[
+    "/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/etc", "/opt",
+];
+
+/// The mechanism used to sandbox processes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backend {
+    bwrap: PathBuf,
+}
+
+impl Backend {
+    /// Determine whether sandboxing is available on this host, or why not.
+    ///
+    /// This is only checked once.
+    pub fn detect() -> Result<&'static Backend, &'static str> {
+        static BACKEND: OnceLock<Result<Backend, String>> = OnceLock::new();
+
+        BACKEND
+            .get_or_init(|| {
+                let Ok(bwrap) = which("bwrap") else {
+                    return Err("bubblewrap (bwrap) is not installed".into());
+                };
+
+                let backend = Backend { bwrap };
+                if !backend.probe() {
+                    return Err(
+                        "bubblewrap does not work, which usually means unprivileged user namespaces are disabled"
+                            .into(),
+                    );
+                }
+
+                Ok(backend)
+            })
+            .as_ref()
+            .map_err(|e| e.as_str())
+    }
+
+    /// Print whether sandboxing is available, or why it is not.
+    pub fn report() {
+        match Self::detect() {
+            Ok(_) => println!("  Sandboxing with bubblewrap"),
+            Err(reason) => println!(
+                "  {}",
+                highlight!(format!(
+                    "Sandboxing is not available on this host: {}. Sandboxed participants will not be run.",
+                    reason
+                ))
+            ),
+        }
+    }
+
+    /// Whether a trivial command can be run in the sandbox.
+    fn probe(&self) -> bool {
+        let mut cmd = Command::new("true");
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        self.apply(cmd, Path::new("/"), &[])
+            .status()
+            .is_ok_and(|s| s.success())
+    }
+
+    /// Produce a command that runs the given command in the sandbox, with
+    /// read-only access to the `location` and `readable` paths.
+    ///
+    /// The working directory is the command's, if set, otherwise the
+    /// `location`.
+    pub fn apply(&self, cmd: Command, location: &Path, readable: &[&Path]) -> Command {
+        let cwd = cmd
+            .get_current_dir()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| location.to_path_buf());
+
+        let mut wrapped = Command::new(&self.bwrap);
+        wrapped.args(bwrap_args(location, readable, &cwd));
+        wrapped.arg(cmd.get_program());
+        wrapped.args(cmd.get_args());
+        wrapped.current_dir(&cwd);
+
+        for (key, value) in cmd.get_envs() {
+            match value {
+                Some(v) => wrapped.env(key, v),
+                None => wrapped.env_remove(key),
+            };
+        }
+
+        wrapped
+    }
+
+    /// The arguments to prefix a shell command with to run it in the sandbox,
+    /// for runners that take commands as strings.
+    pub fn prefix(&self, location: &Path, readable: &[&Path], cwd: &Path) -> String {
+        let mut args = vec![self.bwrap.to_string_lossy().to_string()];
+        args.extend(bwrap_args(location, readable, cwd));
+        shell_words::join(args)
+    }
+}
+
+fn bwrap_args(location: &Path, readable: &[&Path], cwd: &Path) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "--die-with-parent",
+        "--unshare-all",
+        "--new-session",
+        "--proc",
+        "/proc",
+        "--dev",
+        "/dev",
+        "--tmpfs",
+        "/tmp",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+
+    let system = SYSTEM_PATHS.iter().map(Path::new).filter(|p| p.exists());
+
+    for path in system
+        .chain(std::iter::once(location))
+        .chain(readable.iter().copied())
+    {
+        let path = path.to_string_lossy().to_string();
+        args.extend(["--ro-bind".to_string(), path.clone(), path]);
+    }
+
+    args.extend([
+        "--chdir".to_string(),
+        cwd.to_string_lossy().to_string(),
+        "--".to_string(),
+    ]);
+
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn bwrap_args() {
+        let args = super::bwrap_args(
+            Path::new("/participants/foo"),
+            &[Path::new("/inputs/day_001/input-foo")],
+            Path::new("/inputs/day_001"),
+        );
+
+        let joined = args.join(" ");
+        assert!(joined.starts_with("--die-with-parent --unshare-all"));
+        assert!(joined.contains("--ro-bind /participants/foo /participants/foo"));
+        assert!(joined.contains("--ro-bind /inputs/day_001/input-foo /inputs/day_001/input-foo"));
+        assert!(joined.ends_with("--chdir /inputs/day_001 --"));
+        assert!(!args.iter().any(|a| a == "--share-net"));
+    }
+
+    #[test]
+    fn bubblewrap_hides_everything_else() {
+        let Ok(backend) = Backend::detect() else {
+            // not every host has bubblewrap, or allows it to run
+            return;
+        };
+
+        let location = TempDir::new().unwrap();
+        let hidden = TempDir::new().unwrap();
+        std::fs::write(hidden.path().join("secret"), "secret").unwrap();
+
+        let run = |script: String| {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg(script);
+            backend
+                .apply(cmd, location.path(), &[])
+                .stderr(Stdio::null())
+                .status()
+                .unwrap()
+        };
+
+        assert!(run("true".into()).success());
+        assert!(!run(format!("cat {}/secret", hidden.path().display())).success());
+        assert!(!run(format!("touch {}/file", location.path().display())).success());
+    }
+}
@@ -0,0 +1,287 @@
+//! A persistent solver process.
+//!
+//! Projects that set `supports_session` are started once with `AOC_SESSION`
+//! set, after which they are sent one request per line on stdin:
+//!
+//! ```json
+//! {"year": 2023, "day": 1, "input": "/absolute/path/to/input"}
+//! ```
+//!
+//! They must answer each request with a single line on stdout, containing the
+//! solution (or `null` if the day is not implemented) and optionally their own
+//! timings in seconds:
+//!
+//! ```json
+//! {"solution": {"part_one": 1, "part_two": 2}, "timings": {"total": 0.0012}}
+//! ```
+//!
+//! Failures are reported as `{"error": "<message>"}`. The process should exit
+//! once stdin is closed.
+use std::{
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::solution::Solution;
+
+/// How often to check whether a finished session has exited.
+const FINISH_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Serialize)]
+struct Request<'a> {
+    year: usize,
+    day: usize,
+    input: &'a Path,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RawResponse {
+    #[serde(default)]
+    solution: Option<Solution>,
+    #[serde(default)]
+    timings: Option<Timings>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// The timings reported by a participant for a single request, in seconds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    #[serde(default)]
+    pub total: Option<f64>,
+    #[serde(default)]
+    pub part_one: Option<f64>,
+    #[serde(default)]
+    pub part_two: Option<f64>,
+}
+
+/// The answer to a single solve request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    /// The solution, or `None` if the day is not implemented.
+    pub solution: Option<Solution>,
+    pub timings: Option<Timings>,
+}
+
+/// A running session with a participant's solver.
+///
+/// The process is killed if the session is dropped without being finished.
+#[derive(Debug)]
+pub struct Session {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    lines: Receiver<std::io::Result<String>>,
+}
+
+impl Session {
+    /// Start a session from a command that runs the solver in session mode.
+    pub fn start(mut cmd: Command) -> Result<Self> {
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to start session")?;
+
+        let stdin = child.stdin.take();
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Session has no stdout"))?;
+
+        // reading happens on a separate thread so that we can time out when a
+        // participant never answers
+        let (tx, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            lines,
+        })
+    }
+
+    /// Ask the solver to solve the given absolute path to an input.
+    ///
+    /// If the solver does not answer within the `timeout`, it is killed and an
+    /// error is returned. The session should not be reused after any error.
+    pub fn solve(
+        &mut self,
+        year: usize,
+        day: usize,
+        input: &Path,
+        timeout: Option<Duration>,
+    ) -> Result<Response> {
+        if input.is_relative() {
+            bail!("Inputs provided to the solver must be absolute");
+        }
+
+        let request = serde_json::to_string(&Request { year, day, input })?;
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| anyhow!("Session has already been finished"))?;
+        writeln!(stdin, "{}", request)
+            .and_then(|_| stdin.flush())
+            .context("Failed to send request to session")?;
+
+        let line = match timeout {
+            Some(t) => self.lines.recv_timeout(t).map_err(|e| match e {
+                RecvTimeoutError::Timeout => {
+                    let _ = self.child.kill();
+                    anyhow!("Session did not respond within {:?}", t)
+                }
+                RecvTimeoutError::Disconnected => anyhow!("Session exited unexpectedly"),
+            })?,
+            None => self
+                .lines
+                .recv()
+                .map_err(|_| anyhow!("Session exited unexpectedly"))?,
+        }
+        .context("Failed to read response from session")?;
+
+        let raw: RawResponse =
+            serde_json::from_str(&line).context("Session response is in invalid format.")?;
+
+        if let Some(msg) = raw.error {
+            bail!("Failed to solve: {}", msg);
+        }
+
+        Ok(Response {
+            solution: raw.solution,
+            timings: raw.timings,
+        })
+    }
+
+    /// Close stdin and wait for the solver to exit.
+    ///
+    /// If the solver does not exit within the `timeout`, it is killed and an
+    /// error is returned.
+    pub fn finish(mut self, timeout: Option<Duration>) -> Result<()> {
+        drop(self.stdin.take());
+
+        let status = match timeout {
+            Some(t) => {
+                let start = Instant::now();
+                loop {
+                    if let Some(status) = self
+                        .child
+                        .try_wait()
+                        .context("Failed to wait for session")?
+                    {
+                        break status;
+                    }
+
+                    if start.elapsed() >= t {
+                        let _ = self.child.kill();
+                        let _ = self.child.wait();
+                        bail!("Session did not exit within {:?}", t);
+                    }
+
+                    std::thread::sleep(FINISH_POLL_INTERVAL);
+                }
+            }
+            None => self.child.wait().context("Failed to wait for session")?,
+        };
+
+        if !status.success() {
+            bail!("Session exited with {}", status);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if self.stdin.is_some() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo_session(response: &str) -> Session {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(format!("while read -r line; do echo '{}'; done", response));
+        Session::start(cmd).unwrap()
+    }
+
+    #[test]
+    fn solve() {
+        let mut session = echo_session(
+            r#"{"solution": {"part_one": 1, "part_two": "b"}, "timings": {"total": 0.5}}"#,
+        );
+        let input = Path::new("/tmp/input");
+
+        for _ in 0..3 {
+            let res = session.solve(2023, 1, input, None).unwrap();
+            let solution = res.solution.unwrap();
+            assert_eq!(solution.part_one(), &serde_json::json!(1));
+            assert_eq!(solution.part_two(), &serde_json::json!("b"));
+            assert_eq!(res.timings.unwrap().total, Some(0.5));
+        }
+
+        assert!(session.solve(2023, 1, Path::new("input"), None).is_err());
+        session.finish(None).unwrap();
+
+        let mut session = echo_session(r#"{"solution": null}"#);
+        let res = session.solve(2023, 1, input, None).unwrap();
+        assert!(res.solution.is_none());
+        assert!(res.timings.is_none());
+
+        let mut session = echo_session(r#"{"error": "oops"}"#);
+        assert!(session.solve(2023, 1, input, None).is_err());
+    }
+
+    #[test]
+    fn timeout() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg("sleep 10");
+        let mut session = Session::start(cmd).unwrap();
+
+        let err = session
+            .solve(
+                2023,
+                1,
+                Path::new("/tmp/input"),
+                Some(Duration::from_millis(100)),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("did not respond"), "{}", err);
+    }
+
+    #[test]
+    fn finish_timeout() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg("sleep 10");
+        let session = Session::start(cmd).unwrap();
+
+        let start = Instant::now();
+        let err = session
+            .finish(Some(Duration::from_millis(100)))
+            .unwrap_err();
+        assert!(err.to_string().contains("did not exit"), "{}", err);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let session = echo_session(r#"{"solution": null}"#);
+        session.finish(Some(Duration::from_secs(5))).unwrap();
+    }
+}
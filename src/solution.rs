@@ -9,10 +9,15 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::git::Revision;
+
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Solution {
     part_one: Value,
     part_two: Value,
+    /// The revision of the solver that produced this solution, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    revision: Option<Revision>,
 }
 
 impl Solution {
@@ -40,6 +45,10 @@ impl Solution {
     pub fn part_two(&self) -> &Value {
         &self.part_two
     }
+
+    pub fn set_revision(&mut self, revision: Option<Revision>) {
+        self.revision = revision;
+    }
 }
 
 impl Display for Solution {
@@ -0,0 +1,112 @@
+/// The arithmetic mean of the values, or 0 if there are none.
+pub fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// The two-sided p-value of the Mann-Whitney U test for the given samples.
+///
+/// This uses the normal approximation with tie and continuity corrections,
+/// which is reasonable for the sample sizes we collect when benchmarking.
+/// Returns `None` if either sample is empty or the p-value is undefined.
+pub fn mann_whitney_u(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+
+    let n1 = a.len() as f64;
+    let n2 = b.len() as f64;
+    let n = n1 + n2;
+
+    // rank the combined samples, averaging the ranks of ties
+    let mut combined: Vec<(f64, bool)> = a
+        .iter()
+        .map(|v| (*v, true))
+        .chain(b.iter().map(|v| (*v, false)))
+        .collect();
+    combined.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    let mut rank_sum_a = 0.0;
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < combined.len() {
+        let mut j = i;
+        while j + 1 < combined.len() && combined[j + 1].0 == combined[i].0 {
+            j += 1;
+        }
+
+        // ranks are 1-based
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        let count = (j - i + 1) as f64;
+        tie_term += count.powi(3) - count;
+
+        for item in combined.iter().take(j + 1).skip(i) {
+            if item.1 {
+                rank_sum_a += rank;
+            }
+        }
+
+        i = j + 1;
+    }
+
+    let u = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let mean_u = n1 * n2 / 2.0;
+    let var_u = n1 * n2 / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+
+    if var_u <= 0.0 {
+        return None;
+    }
+
+    let diff = (u - mean_u).abs();
+    let z = (diff - 0.5).max(0.0) / var_u.sqrt();
+
+    Some(erfc(z / std::f64::consts::SQRT_2).min(1.0))
+}
+
+/// The complementary error function.
+///
+/// This is the Chebyshev approximation from Numerical Recipes, with a
+/// fractional error of less than 1.2e-7.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t
+        * (-z * z - 1.265_512_23
+            + t * (1.000_023_68
+                + t * (0.374_091_96
+                    + t * (0.096_784_18
+                        + t * (-0.186_288_06
+                            + t * (0.278_868_07
+                                + t * (-1.135_203_98
+                                    + t * (1.488_515_87
+                                        + t * (-0.822_152_23 + t * 0.170_872_77)))))))))
+            .exp();
+
+    if x >= 0.0 { r } else { 2.0 - r }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mann_whitney() {
+        let a = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let b = [6.0, 7.0, 8.0, 9.0, 10.0];
+        let p = mann_whitney_u(&a, &b).unwrap();
+        assert!((p - 0.01219).abs() < 1e-4, "{}", p);
+
+        // symmetric
+        let p2 = mann_whitney_u(&b, &a).unwrap();
+        assert!((p - p2).abs() < 1e-12);
+
+        let p = mann_whitney_u(&a, &a).unwrap();
+        assert!(p > 0.9);
+
+        assert!(mann_whitney_u(&a, &[]).is_none());
+        assert!(mann_whitney_u(&[1.0, 1.0], &[1.0, 1.0]).is_none());
+    }
+}